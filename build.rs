@@ -61,12 +61,211 @@ mod config_defs {
         }
     }
 
+    fn option_string(value: &Option<String>) -> TokenStream {
+        match value {
+            Some(value) => quote! { Some(#value.to_string()) },
+            None => quote! { None },
+        }
+    }
+
+    fn option_password(value: &Option<Password>) -> TokenStream {
+        match value {
+            Some(password) => quote! { Some(Password(#password.to_string())) },
+            None => quote! { None },
+        }
+    }
+
+    fn option_copy<T: ToTokens>(value: &Option<T>) -> TokenStream {
+        match value {
+            Some(value) => quote! { Some(#value) },
+            None => quote! { None },
+        }
+    }
+
+    impl ToTokens for BackupTarget {
+        fn to_tokens(&self, tokens: &mut TokenStream) {
+            tokens.extend(match self {
+                BackupTarget::Local { path } => quote! {
+                    crate::config_defs::BackupTarget::Local { path: #path.to_string() }
+                },
+                BackupTarget::S3 {
+                    endpoint,
+                    bucket,
+                    region,
+                    prefix,
+                    access_key,
+                    secret_key,
+                } => {
+                    let region = option_string(region);
+                    let prefix = option_string(prefix);
+                    let access_key = option_string(access_key);
+                    let secret_key = option_password(secret_key);
+                    quote! {
+                        crate::config_defs::BackupTarget::S3 {
+                            endpoint: #endpoint.to_string(),
+                            bucket: #bucket.to_string(),
+                            region: #region,
+                            prefix: #prefix,
+                            access_key: #access_key,
+                            secret_key: #secret_key,
+                        }
+                    }
+                }
+                BackupTarget::Sftp {
+                    host,
+                    user,
+                    port,
+                    path,
+                    identity_file,
+                } => {
+                    let user = option_string(user);
+                    let port = option_copy(port);
+                    let identity_file = option_string(identity_file);
+                    quote! {
+                        crate::config_defs::BackupTarget::Sftp {
+                            host: #host.to_string(),
+                            user: #user,
+                            port: #port,
+                            path: #path.to_string(),
+                            identity_file: #identity_file,
+                        }
+                    }
+                }
+                BackupTarget::Rsync { destination } => quote! {
+                    crate::config_defs::BackupTarget::Rsync { destination: #destination.to_string() }
+                },
+            })
+        }
+    }
+
+    impl ToTokens for BackupEncryption {
+        fn to_tokens(&self, tokens: &mut TokenStream) {
+            let passphrase_file = option_string(&self.passphrase_file);
+            let passphrase_env = option_string(&self.passphrase_env);
+            let recipients = &self.recipients;
+            let identity_file = option_string(&self.identity_file);
+
+            tokens.extend(quote! {
+                crate::config_defs::BackupEncryption {
+                    passphrase_file: #passphrase_file,
+                    passphrase_env: #passphrase_env,
+                    recipients: vec![#(#recipients.to_string()),*],
+                    identity_file: #identity_file,
+                }
+            })
+        }
+    }
+
+    impl ToTokens for BackupConfig {
+        fn to_tokens(&self, tokens: &mut TokenStream) {
+            let directory = option_string(&self.directory);
+            let retention = option_copy(&self.retention);
+            let encryption = option_copy(&self.encryption);
+
+            let targets = self.targets.iter().map(|(k, v)| {
+                quote! { ( #k.to_string(), #v ) }
+            });
+
+            tokens.extend(quote! {
+                BackupConfig {
+                    directory: #directory,
+                    retention: #retention,
+                    encryption: #encryption,
+                    targets: std::collections::HashMap::from([
+                        #(#targets),*
+                    ]),
+                }
+            })
+        }
+    }
+
+    impl ToTokens for PortRange {
+        fn to_tokens(&self, tokens: &mut TokenStream) {
+            let start = self.start;
+            let end = self.end;
+            tokens.extend(quote! {
+                crate::config_defs::PortRange {
+                    start: #start,
+                    end: #end,
+                }
+            })
+        }
+    }
+
+    impl ToTokens for NetworkConfig {
+        fn to_tokens(&self, tokens: &mut TokenStream) {
+            let proxy = &self.proxy;
+            let backends = &self.backends;
+            tokens.extend(quote! {
+                crate::config_defs::NetworkConfig {
+                    proxy: #proxy.to_string(),
+                    backends: vec![#(#backends.to_string()),*],
+                }
+            })
+        }
+    }
+
+    impl ToTokens for ProviderSource {
+        fn to_tokens(&self, tokens: &mut TokenStream) {
+            tokens.extend(match self {
+                ProviderSource::Template {
+                    url,
+                    versions,
+                    checksum_url,
+                } => {
+                    let checksum_url = option_string(checksum_url);
+                    quote! {
+                        crate::config_defs::ProviderSource::Template {
+                            url: #url.to_string(),
+                            versions: vec![#(#versions.to_string()),*],
+                            checksum_url: #checksum_url,
+                        }
+                    }
+                }
+                ProviderSource::Json { url } => quote! {
+                    crate::config_defs::ProviderSource::Json { url: #url.to_string() }
+                },
+            })
+        }
+    }
+
+    impl ToTokens for ProviderConfig {
+        fn to_tokens(&self, tokens: &mut TokenStream) {
+            let source = &self.source;
+            let proxy = self.proxy;
+            let java_major = option_copy(&self.java_major);
+            let launch = option_string(&self.launch);
+            let post_install = option_string(&self.post_install);
+            tokens.extend(quote! {
+                crate::config_defs::ProviderConfig {
+                    source: #source,
+                    proxy: #proxy,
+                    java_major: #java_major,
+                    launch: #launch,
+                    post_install: #post_install,
+                }
+            })
+        }
+    }
+
     impl ToTokens for DynamicConfig {
         fn to_tokens(&self, tokens: &mut TokenStream) {
             let default_java_args = &self.default_java_args;
+            let java_paths = self.java_paths.iter().map(|(k, v)| {
+                quote! { ( #k.to_string(), #v.to_string() ) }
+            });
             let nogui = &self.nogui;
             let servers_directory = &self.servers_directory;
+            let templates_directory = option_string(&self.templates_directory);
             let default_server = &self.default_server;
+            let port_range = &self.port_range;
+            let backup = &self.backup;
+            let networks = self.networks.iter().map(|(k, v)| {
+                quote! { ( #k.to_string(), #v ) }
+            });
+            let providers = self.providers.iter().map(|(k, v)| {
+                quote! { ( #k.to_string(), #v ) }
+            });
 
             let key_value_pairs = self.rcon.iter().map(|(k, v)| {
                 quote! { ( #k.to_string(), #v )}
@@ -75,12 +274,24 @@ mod config_defs {
             tokens.extend(quote! {
                 DynamicConfig {
                     default_java_args: #default_java_args.to_string(),
+                    java_paths: std::collections::HashMap::from([
+                        #(#java_paths),*
+                    ]),
                     nogui: #nogui,
                     servers_directory: #servers_directory.to_string(),
+                    templates_directory: #templates_directory,
                     default_server: #default_server.to_string(),
+                    port_range: #port_range,
                     rcon: std::collections::HashMap::from([
                         #(#key_value_pairs),*
                     ]),
+                    backup: #backup,
+                    networks: std::collections::HashMap::from([
+                        #(#networks),*
+                    ]),
+                    providers: std::collections::HashMap::from([
+                        #(#providers),*
+                    ]),
                 }
             });
         }
@@ -186,7 +397,9 @@ fn main() -> Result<()> {
 
     let tokens = quote! {
         mod generated_cfg {
-            use crate::config_defs::{StaticConfig, Password, RconConfig, DynamicConfig};
+            use crate::config_defs::{
+                BackupConfig, DynamicConfig, Password, RconConfig, StaticConfig,
+            };
             use std::sync::OnceLock;
 
             pub const STATIC_CONFIG: StaticConfig = #static_config;
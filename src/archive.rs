@@ -0,0 +1,115 @@
+use crate::error::{Error, Result};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use std::{
+    fs::{self, File},
+    io::{self, BufWriter, Read, Write},
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const EXTENSION: &str = ".tar.gz";
+
+pub struct Writer<W: Write> {
+    builder: tar::Builder<GzEncoder<W>>,
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(writer: W) -> Self {
+        let mut builder = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+        builder.follow_symlinks(false);
+        Self { builder }
+    }
+
+    // paths passed to `exclude` are relative to `src_dir`
+    pub fn append_dir_filtered<S, R, F>(&mut self, src_dir: S, root: R, exclude: F) -> Result<()>
+    where
+        S: AsRef<Path>,
+        R: AsRef<Path>,
+        F: Fn(&Path) -> bool,
+    {
+        let src_dir = src_dir.as_ref();
+        let root = root.as_ref();
+
+        self.builder.append_dir(root, src_dir)?;
+        self.append_children(src_dir, root, Path::new(""), &exclude)
+    }
+
+    fn append_children(
+        &mut self,
+        src_dir: &Path,
+        root: &Path,
+        relative: &Path,
+        exclude: &impl Fn(&Path) -> bool,
+    ) -> Result<()> {
+        let mut entries = fs::read_dir(src_dir.join(relative))?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+
+        for entry in entries {
+            let relative = relative.join(entry.file_name());
+            if exclude(&relative) {
+                continue;
+            }
+
+            if entry.file_type()?.is_dir() {
+                self.builder
+                    .append_dir(root.join(&relative), entry.path())?;
+                self.append_children(src_dir, root, &relative, exclude)?;
+            } else {
+                self.builder
+                    .append_path_with_name(entry.path(), root.join(&relative))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn append_file(
+        &mut self,
+        path: impl AsRef<Path>,
+        contents: &[u8],
+        mode: u32,
+    ) -> Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(mode);
+        header.set_mtime(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_err(|_| Error::TimeWentBackwards)?
+                .as_secs(),
+        );
+        self.builder.append_data(&mut header, path, contents)?;
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<W> {
+        Ok(self.builder.into_inner()?.finish()?)
+    }
+}
+
+pub fn write<S, W, R>(src_dir: S, writer: W, root: R) -> Result<W>
+where
+    S: AsRef<Path>,
+    W: Write,
+    R: AsRef<Path>,
+{
+    let mut builder = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+    builder.follow_symlinks(false);
+    builder.append_dir_all(root, src_dir)?;
+    Ok(builder.into_inner()?.finish()?)
+}
+
+pub fn create<S, A, R>(src_dir: S, archive: A, root: R) -> Result<()>
+where
+    S: AsRef<Path>,
+    A: AsRef<Path>,
+    R: AsRef<Path>,
+{
+    write(src_dir, BufWriter::new(File::create(archive)?), root)?.flush()?;
+    Ok(())
+}
+
+pub fn unpack(reader: impl Read, dst_dir: impl AsRef<Path>) -> Result<()> {
+    tar::Archive::new(GzDecoder::new(reader)).unpack(dst_dir)?;
+    Ok(())
+}
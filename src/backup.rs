@@ -0,0 +1,583 @@
+use crate::{
+    archive,
+    config::{self, get_config_directory, get_expanded_servers_dir},
+    config_defs::BackupTarget,
+    encryption,
+    error::{Error, Result},
+    s3::Bucket,
+    server::{get_server_dir_required, remove_dir_with_retries},
+    session,
+};
+use std::{
+    env,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::{self, Command, Stdio},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const BACKUPS_DIRECTORY: &str = "backups";
+const RESTORE_DIRECTORY_PREFIX: &str = ".restore-";
+
+pub fn get_backups_dir() -> Result<PathBuf> {
+    let directory = config::get()?.backup.directory.clone();
+
+    match directory {
+        Some(directory) => Ok(PathBuf::from(&*shellexpand::full(&directory)?)),
+        None => Ok(get_config_directory()?.join(BACKUPS_DIRECTORY)),
+    }
+}
+
+fn get_server_backups_dir(server: &str) -> Result<PathBuf> {
+    Ok(get_backups_dir()?.join(server))
+}
+
+fn is_backup(file_name: &str) -> bool {
+    file_name
+        .strip_suffix(encryption::EXTENSION)
+        .unwrap_or(file_name)
+        .ends_with(archive::EXTENSION)
+}
+
+fn sorted_backups(mut backups: Vec<String>) -> Vec<String> {
+    backups.retain(|backup| is_backup(backup));
+    backups.sort();
+    backups
+}
+
+fn list_local(server: &str) -> Result<Vec<String>> {
+    let dir = get_server_backups_dir(server)?;
+
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut backups = vec![];
+    for entry in fs::read_dir(dir)? {
+        backups.push(entry?.file_name().to_string_lossy().to_string());
+    }
+
+    Ok(sorted_backups(backups))
+}
+
+fn get_target(name: &str) -> Result<BackupTarget> {
+    config::get()?
+        .backup
+        .targets
+        .get(name)
+        .cloned()
+        .ok_or_else(|| Error::BackupTargetNotFound(name.to_string()))
+}
+
+fn run_command(command: &mut Command) -> Result<String> {
+    let output = command.stdin(Stdio::null()).output()?;
+
+    if !output.status.success() {
+        return Err(Error::CommandFailure {
+            code: output.status.code(),
+            stderr: Some(output.stderr),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn run_sftp_batch(
+    host: &str,
+    user: Option<&str>,
+    port: Option<u16>,
+    identity_file: Option<&str>,
+    batch: &str,
+) -> Result<String> {
+    let mut command = Command::new("sftp");
+    command.arg("-b").arg("-");
+
+    if let Some(port) = port {
+        command.arg("-P").arg(port.to_string());
+    }
+
+    if let Some(identity_file) = identity_file {
+        command.arg("-i").arg(&*shellexpand::full(identity_file)?);
+    }
+
+    command.arg(match user {
+        Some(user) => format!("{user}@{host}"),
+        None => host.to_string(),
+    });
+
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    child
+        .stdin
+        .take()
+        .ok_or(io::Error::new(
+            io::ErrorKind::BrokenPipe,
+            "Failed to take stdin pipe",
+        ))?
+        .write_all(batch.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        return Err(Error::CommandFailure {
+            code: output.status.code(),
+            stderr: Some(output.stderr),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn remote_key(prefix: Option<&str>, server: &str, backup: &str) -> String {
+    match prefix.map(|prefix| prefix.trim_matches('/')) {
+        Some(prefix) if !prefix.is_empty() => format!("{prefix}/{server}/{backup}"),
+        _ => format!("{server}/{backup}"),
+    }
+}
+
+fn list_remote(target: &BackupTarget, server: &str) -> Result<Vec<String>> {
+    let backups = match target {
+        BackupTarget::Local { path } => {
+            let dir = Path::new(&*shellexpand::full(path)?).join(server);
+            if !dir.is_dir() {
+                return Ok(vec![]);
+            }
+
+            let mut backups = vec![];
+            for entry in fs::read_dir(dir)? {
+                backups.push(entry?.file_name().to_string_lossy().to_string());
+            }
+            backups
+        }
+        BackupTarget::S3 {
+            endpoint,
+            bucket,
+            region,
+            prefix,
+            access_key,
+            secret_key,
+        } => Bucket::new(
+            endpoint,
+            bucket,
+            region.as_deref(),
+            access_key.as_deref(),
+            secret_key.as_ref(),
+        )?
+        .list(&remote_key(prefix.as_deref(), server, ""))?
+        .into_iter()
+        .filter_map(|key| key.rsplit('/').next().map(String::from))
+        .collect(),
+        BackupTarget::Sftp {
+            host,
+            user,
+            port,
+            path,
+            identity_file,
+        } => run_sftp_batch(
+            host,
+            user.as_deref(),
+            *port,
+            identity_file.as_deref(),
+            &format!("-ls -1 \"{path}/{server}\"\n"),
+        )?
+        .lines()
+        .filter(|line| !line.starts_with("sftp>"))
+        .filter_map(|line| line.trim().rsplit('/').next().map(String::from))
+        .collect(),
+        BackupTarget::Rsync { destination } => {
+            match run_command(
+                Command::new("rsync")
+                    .arg("--list-only")
+                    .arg(format!("{destination}/{server}/")),
+            ) {
+                Ok(output) => output
+                    .lines()
+                    .filter_map(|line| line.split_whitespace().last().map(String::from))
+                    .collect(),
+                // the server directory does not exist on the destination yet
+                Err(Error::CommandFailure { code: Some(23), .. }) => vec![],
+                Err(err) => return Err(err),
+            }
+        }
+    };
+
+    Ok(sorted_backups(backups))
+}
+
+fn upload(target: &BackupTarget, server: &str, backup_path: &Path) -> Result<()> {
+    let backup = backup_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    match target {
+        BackupTarget::Local { path } => {
+            let dir = Path::new(&*shellexpand::full(path)?).join(server);
+            fs::create_dir_all(&dir)?;
+            fs::copy(backup_path, dir.join(backup))?;
+        }
+        BackupTarget::S3 {
+            endpoint,
+            bucket,
+            region,
+            prefix,
+            access_key,
+            secret_key,
+        } => Bucket::new(
+            endpoint,
+            bucket,
+            region.as_deref(),
+            access_key.as_deref(),
+            secret_key.as_ref(),
+        )?
+        .put_file(&remote_key(prefix.as_deref(), server, &backup), backup_path)?,
+        BackupTarget::Sftp {
+            host,
+            user,
+            port,
+            path,
+            identity_file,
+        } => {
+            run_sftp_batch(
+                host,
+                user.as_deref(),
+                *port,
+                identity_file.as_deref(),
+                &format!(
+                    "-mkdir \"{path}\"\n-mkdir \"{path}/{server}\"\nput \"{}\" \"{path}/{server}/{backup}\"\n",
+                    backup_path.display()
+                ),
+            )?;
+        }
+        BackupTarget::Rsync { destination } => {
+            // the `./` marks where the relative path starts, so that the server directory is
+            // created on the destination along with the backup
+            run_command(
+                Command::new("rsync")
+                    .arg("-a")
+                    .arg("--relative")
+                    .arg(format!(
+                        "{}/./{server}/{backup}",
+                        get_backups_dir()?.display()
+                    ))
+                    .arg(format!("{destination}/")),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn download(target: &BackupTarget, server: &str, backup: &str, dst: &Path) -> Result<()> {
+    match target {
+        BackupTarget::Local { path } => {
+            fs::copy(
+                Path::new(&*shellexpand::full(path)?)
+                    .join(server)
+                    .join(backup),
+                dst,
+            )?;
+        }
+        BackupTarget::S3 {
+            endpoint,
+            bucket,
+            region,
+            prefix,
+            access_key,
+            secret_key,
+        } => Bucket::new(
+            endpoint,
+            bucket,
+            region.as_deref(),
+            access_key.as_deref(),
+            secret_key.as_ref(),
+        )?
+        .get_file(&remote_key(prefix.as_deref(), server, backup), dst)?,
+        BackupTarget::Sftp {
+            host,
+            user,
+            port,
+            path,
+            identity_file,
+        } => {
+            run_sftp_batch(
+                host,
+                user.as_deref(),
+                *port,
+                identity_file.as_deref(),
+                &format!("get \"{path}/{server}/{backup}\" \"{}\"\n", dst.display()),
+            )?;
+        }
+        BackupTarget::Rsync { destination } => {
+            run_command(
+                Command::new("rsync")
+                    .arg(format!("{destination}/{server}/{backup}"))
+                    .arg(dst),
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+fn delete_remote(target: &BackupTarget, server: &str, backup: &str) -> Result<()> {
+    match target {
+        BackupTarget::Local { path } => {
+            fs::remove_file(
+                Path::new(&*shellexpand::full(path)?)
+                    .join(server)
+                    .join(backup),
+            )?;
+        }
+        BackupTarget::S3 {
+            endpoint,
+            bucket,
+            region,
+            prefix,
+            access_key,
+            secret_key,
+        } => Bucket::new(
+            endpoint,
+            bucket,
+            region.as_deref(),
+            access_key.as_deref(),
+            secret_key.as_ref(),
+        )?
+        .delete(&remote_key(prefix.as_deref(), server, backup))?,
+        BackupTarget::Sftp {
+            host,
+            user,
+            port,
+            path,
+            identity_file,
+        } => {
+            run_sftp_batch(
+                host,
+                user.as_deref(),
+                *port,
+                identity_file.as_deref(),
+                &format!("rm \"{path}/{server}/{backup}\"\n"),
+            )?;
+        }
+        // rsync can only delete by syncing, so an empty directory is synced over the server's
+        // directory with every file but the backup filtered out
+        BackupTarget::Rsync { destination } => {
+            let empty_dir = env::temp_dir().join(format!("mcserver-rsync-{}", process::id()));
+            fs::create_dir_all(&empty_dir)?;
+
+            let result = run_command(
+                Command::new("rsync")
+                    .arg("-r")
+                    .arg("--delete")
+                    .arg(format!("--include=/{backup}"))
+                    .arg("--exclude=*")
+                    .arg(format!("{}/", empty_dir.display()))
+                    .arg(format!("{destination}/{server}/")),
+            );
+            fs::remove_dir_all(&empty_dir)?;
+            result?;
+        }
+    }
+
+    Ok(())
+}
+
+fn apply_retention(
+    backups: Vec<String>,
+    retention: Option<usize>,
+    mut remove: impl FnMut(&str) -> Result<()>,
+) -> Result<()> {
+    let Some(retention) = retention else {
+        return Ok(());
+    };
+
+    let excess = backups.len().saturating_sub(retention);
+    for backup in &backups[..excess] {
+        remove(backup)?;
+    }
+
+    Ok(())
+}
+
+pub fn create(server: impl AsRef<str>) -> Result<PathBuf> {
+    let server = server.as_ref();
+    let server_dir = get_server_dir_required(server)?;
+
+    let backups_dir = get_server_backups_dir(server)?;
+    fs::create_dir_all(&backups_dir)?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| Error::TimeWentBackwards)?
+        .as_secs();
+
+    let (retention, encryption, targets) = {
+        let config = config::get()?;
+        (
+            config.backup.retention,
+            config.backup.encryption.clone(),
+            config.backup.targets.clone(),
+        )
+    };
+
+    println!("Backing up {server}...");
+
+    let backup_path = match &encryption {
+        Some(encryption) => {
+            let backup_path = backups_dir.join(format!(
+                "{server}-{timestamp}{}{}",
+                archive::EXTENSION,
+                encryption::EXTENSION
+            ));
+
+            let file = BufWriter::new(File::create(&backup_path)?);
+            let mut writer = encryption::encryptor(encryption)?.wrap_output(file)?;
+            archive::write(&server_dir, &mut writer, server)?;
+            writer.finish()?.flush()?;
+
+            backup_path
+        }
+        None => {
+            let backup_path =
+                backups_dir.join(format!("{server}-{timestamp}{}", archive::EXTENSION));
+            archive::create(&server_dir, &backup_path, server)?;
+            backup_path
+        }
+    };
+
+    apply_retention(list_local(server)?, retention, |backup| {
+        Ok(fs::remove_file(backups_dir.join(backup))?)
+    })?;
+
+    let mut failed = vec![];
+
+    for (name, target) in &targets {
+        println!("Uploading backup to {name}...");
+
+        let result = upload(target, server, &backup_path).and_then(|_| {
+            apply_retention(list_remote(target, server)?, retention, |backup| {
+                delete_remote(target, server, backup)
+            })
+        });
+
+        if let Err(err) = result {
+            eprintln!("Failed to upload backup to {name}: {err}");
+            failed.push(name.as_str());
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(Error::BackupUploadFailed(failed.join(", ")));
+    }
+
+    println!("Backup saved to {}", backup_path.display());
+    Ok(backup_path)
+}
+
+pub fn list(server: impl AsRef<str>, target: Option<impl AsRef<str>>) -> Result<Vec<String>> {
+    let server = server.as_ref();
+
+    match target {
+        Some(target) => list_remote(&get_target(target.as_ref())?, server),
+        None => list_local(server),
+    }
+}
+
+fn unpack_backup(backup_path: &Path, dst_dir: &Path) -> Result<()> {
+    let file = BufReader::new(File::open(backup_path)?);
+
+    if !backup_path
+        .to_string_lossy()
+        .ends_with(encryption::EXTENSION)
+    {
+        return archive::unpack(file, dst_dir);
+    }
+
+    let encryption = config::get()?
+        .backup
+        .encryption
+        .clone()
+        .ok_or_else(|| Error::MissingCredentials("backup encryption".to_string()))?;
+
+    archive::unpack(encryption::decrypt(&encryption, file)?, dst_dir)
+}
+
+pub fn restore(
+    server: impl AsRef<str>,
+    backup: Option<impl AsRef<str>>,
+    target: Option<impl AsRef<str>>,
+) -> Result<()> {
+    let server = server.as_ref();
+
+    if session::is_active(server)? {
+        return Err(Error::ServerActive(server.to_string()));
+    }
+
+    let target = target
+        .map(|target| get_target(target.as_ref()))
+        .transpose()?;
+
+    let backups = match &target {
+        Some(target) => list_remote(target, server)?,
+        None => list_local(server)?,
+    };
+
+    let backup = match backup {
+        Some(backup) => backups
+            .into_iter()
+            .find(|b| b == backup.as_ref())
+            .ok_or_else(|| Error::BackupNotFound(backup.as_ref().to_string()))?,
+        None => backups
+            .into_iter()
+            .next_back()
+            .ok_or_else(|| Error::BackupNotFound(format!("for server {server}")))?,
+    };
+
+    let backups_dir = get_server_backups_dir(server)?;
+    let backup_path = backups_dir.join(&backup);
+
+    if let Some(target) = &target
+        && !backup_path.exists()
+    {
+        println!("Downloading {backup}...");
+        fs::create_dir_all(&backups_dir)?;
+        download(target, server, &backup, &backup_path)?;
+    }
+
+    println!("Restoring {server} from {backup}...");
+
+    let servers_dir = get_expanded_servers_dir()?;
+    let restore_dir = servers_dir.join(format!("{RESTORE_DIRECTORY_PREFIX}{server}"));
+
+    if restore_dir.exists() {
+        remove_dir_with_retries(&restore_dir)?;
+    }
+
+    fs::create_dir_all(&restore_dir)?;
+
+    if let Err(err) = unpack_backup(&backup_path, &restore_dir) {
+        remove_dir_with_retries(&restore_dir)?;
+        return Err(err);
+    }
+
+    // archives contain a single root directory named after the server they were made from
+    let Some(extracted_dir) = fs::read_dir(&restore_dir)?.next().transpose()? else {
+        remove_dir_with_retries(&restore_dir)?;
+        return Err(Error::BackupNotFound(backup));
+    };
+
+    let server_dir = servers_dir.join(server);
+    if server_dir.exists() {
+        remove_dir_with_retries(&server_dir)?;
+    }
+
+    fs::rename(extracted_dir.path(), &server_dir)?;
+    remove_dir_with_retries(&restore_dir)?;
+
+    Ok(())
+}
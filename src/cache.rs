@@ -0,0 +1,461 @@
+use crate::{
+    checksum::{self, Algorithm, Checksum},
+    config::{get_config_directory, get_expanded_servers_dir},
+    error::{Error, Result},
+    platforms::{self, Download, Platform},
+    progress::{self, Progress},
+    server,
+};
+use reqwest::{
+    StatusCode,
+    blocking::Response,
+    header::{CONTENT_DISPOSITION, ETAG, IF_RANGE, LAST_MODIFIED, RANGE},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+use url::Url;
+
+const CACHE_DIRECTORY: &str = "cache";
+const JARS_DIRECTORY: &str = "jars";
+const OBJECTS_DIRECTORY: &str = "objects";
+const PARTIAL_DIRECTORY: &str = "partial";
+const ENTRY_EXTENSION: &str = "toml";
+const PARTIAL_EXTENSION: &str = "part";
+
+// maps a platform/version/build to the jar stored under its sha256 in the objects directory
+#[derive(Debug, Deserialize, Serialize)]
+struct Entry {
+    file_name: String,
+    sha256: String,
+    checksum: Checksum,
+}
+
+// what a partial download was fetched from, and the validator that a resume must match
+#[derive(Debug, Deserialize, Serialize)]
+struct Partial {
+    url: String,
+    validator: Option<String>,
+}
+
+pub struct CachedJar {
+    pub path: PathBuf,
+    pub file_name: String,
+    pub checksum: Checksum,
+}
+
+pub fn get_cache_dir() -> Result<PathBuf> {
+    Ok(get_config_directory()?.join(CACHE_DIRECTORY))
+}
+
+fn get_object_path(sha256: &str) -> Result<PathBuf> {
+    Ok(get_cache_dir()?
+        .join(OBJECTS_DIRECTORY)
+        .join(format!("{sha256}.jar")))
+}
+
+// a build or a published checksum identifies the exact jar, anything else is stored as an object
+// but never looked up again
+fn is_pinned(download: &Download) -> bool {
+    download.build.is_some() || download.checksum.is_some()
+}
+
+fn get_entry_path(platform: &Platform, download: &Download, extension: &str) -> Result<PathBuf> {
+    Ok(get_cache_dir()?
+        .join(JARS_DIRECTORY)
+        .join(platform.to_string())
+        .join(&download.version)
+        .join(format!(
+            "{}.{extension}",
+            download.build.as_deref().unwrap_or("unknown")
+        )))
+}
+
+// partial downloads are keyed by their url, so that a resume never continues a different file
+fn get_partial_path(url: &Url, extension: &str) -> Result<PathBuf> {
+    let key = checksum::copy(url.as_str().as_bytes(), io::sink(), Algorithm::Sha256)?.hash;
+
+    Ok(get_cache_dir()?
+        .join(PARTIAL_DIRECTORY)
+        .join(format!("{key}.{extension}")))
+}
+
+fn read_partial(path: &Path) -> Result<Option<Partial>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    Ok(Some(toml::from_str(&fs::read_to_string(path)?)?))
+}
+
+fn remove_partial(part_path: &Path, partial_path: &Path) -> Result<()> {
+    for path in [part_path, partial_path] {
+        if path.is_file() {
+            fs::remove_file(path)?;
+        }
+    }
+
+    Ok(())
+}
+
+// weak etags can't be used in If-Range, so the last modified date is used instead
+fn get_validator(response: &Response) -> Option<String> {
+    let header = |name| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .map(String::from)
+    };
+
+    header(ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(LAST_MODIFIED))
+}
+
+fn is_partial(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == PARTIAL_EXTENSION)
+}
+
+fn hash_file(path: &Path, algorithm: Algorithm) -> Result<Checksum> {
+    checksum::copy(File::open(path)?, io::sink(), algorithm)
+}
+
+fn read_entry(path: &Path) -> Result<Option<Entry>> {
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    Ok(Some(toml::from_str(&fs::read_to_string(path)?)?))
+}
+
+fn get_file_name(response: &Response) -> Result<Option<String>> {
+    Ok(response
+        .headers()
+        .get(CONTENT_DISPOSITION)
+        .map(|disposition| disposition.to_str())
+        .transpose()?
+        .and_then(|cd| cd.split("filename=\"").nth(1))
+        .and_then(|slice| slice.split('"').next())
+        .map(String::from))
+}
+
+// appends to an existing partial file when the server supports range requests and confirms
+// through If-Range that the file hasn't changed since
+fn download_to(url: &Url, part_path: &Path, partial_path: &Path) -> Result<Option<String>> {
+    let validator = read_partial(partial_path)?
+        .filter(|partial| partial.url == url.as_str())
+        .and_then(|partial| partial.validator);
+
+    // without a validator there is no telling whether the rest still belongs to the same file
+    if validator.is_none() {
+        remove_partial(part_path, partial_path)?;
+    }
+
+    let offset = fs::metadata(part_path).map_or(0, |metadata| metadata.len());
+
+    println!("Downloading from {url}...");
+
+    let mut request = platforms::get_client()?.get(url.clone());
+    if offset > 0
+        && let Some(validator) = &validator
+    {
+        request = request
+            .header(RANGE, format!("bytes={offset}-"))
+            .header(IF_RANGE, validator);
+    }
+
+    let response = request.send()?;
+
+    // the partial file no longer matches what the server has
+    if offset > 0
+        && (response.status() == StatusCode::RANGE_NOT_SATISFIABLE
+            || response.status() == StatusCode::PARTIAL_CONTENT
+                && get_validator(&response) != validator)
+    {
+        remove_partial(part_path, partial_path)?;
+        return download_to(url, part_path, partial_path);
+    }
+
+    let mut response = response.error_for_status()?;
+    let file_name = get_file_name(&response)?;
+
+    let (mut file, offset) = if response.status() == StatusCode::PARTIAL_CONTENT {
+        println!(
+            "Resuming download after {}",
+            progress::format_bytes(offset as f64)
+        );
+        (OpenOptions::new().append(true).open(part_path)?, offset)
+    } else {
+        fs::write(
+            partial_path,
+            toml::to_string(&Partial {
+                url: url.to_string(),
+                validator: get_validator(&response),
+            })?,
+        )?;
+        (File::create(part_path)?, 0)
+    };
+
+    let mut progress = Progress::new(response.content_length().map(|len| len + offset), offset);
+    let mut buffer = [0; 64 * 1024];
+
+    loop {
+        let read = match response.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(Error::Io(err)),
+        };
+
+        file.write_all(&buffer[..read])?;
+        progress.advance(read as u64);
+    }
+
+    file.flush()?;
+    progress.finish();
+
+    Ok(file_name)
+}
+
+pub fn get_jar(platform: &Platform, download: &Download) -> Result<CachedJar> {
+    let entry_path = get_entry_path(platform, download, ENTRY_EXTENSION)?;
+    let part_path = get_partial_path(&download.url, PARTIAL_EXTENSION)?;
+    let partial_path = get_partial_path(&download.url, ENTRY_EXTENSION)?;
+
+    if is_pinned(download)
+        && let Some(entry) = read_entry(&entry_path)?
+        && download
+            .checksum
+            .as_ref()
+            .is_none_or(|checksum| *checksum == entry.checksum)
+    {
+        let object_path = get_object_path(&entry.sha256)?;
+
+        if object_path.is_file() && hash_file(&object_path, Algorithm::Sha256)?.hash == entry.sha256
+        {
+            match &download.build {
+                Some(build) => {
+                    println!("Using cached {platform} {} build {build}", download.version)
+                }
+                None => println!("Using cached {platform} {}", download.version),
+            }
+
+            return Ok(CachedJar {
+                path: object_path,
+                file_name: entry.file_name,
+                checksum: entry.checksum,
+            });
+        }
+
+        eprintln!("Warning: the cached jar is missing or corrupt, downloading it again");
+    }
+
+    fs::create_dir_all(get_cache_dir()?.join(OBJECTS_DIRECTORY))?;
+    if let Some(parent) = part_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let file_name = download_to(&download.url, &part_path, &partial_path)?
+        .unwrap_or_else(|| format!("{platform}.jar"));
+
+    let sha256 = hash_file(&part_path, Algorithm::Sha256)?;
+    let checksum = match &download.checksum {
+        Some(expected) => {
+            let actual = hash_file(&part_path, expected.algorithm)?;
+
+            if *expected != actual {
+                remove_partial(&part_path, &partial_path)?;
+                return Err(Error::ChecksumMismatch {
+                    file: file_name,
+                    expected: expected.to_string(),
+                    actual: actual.to_string(),
+                });
+            }
+
+            actual
+        }
+        None => sha256.clone(),
+    };
+
+    let object_path = get_object_path(&sha256.hash)?;
+    fs::rename(&part_path, &object_path)?;
+    remove_partial(&part_path, &partial_path)?;
+
+    if is_pinned(download) {
+        fs::write(
+            &entry_path,
+            toml::to_string(&Entry {
+                file_name: file_name.clone(),
+                sha256: sha256.hash,
+                checksum: checksum.clone(),
+            })?,
+        )?;
+    }
+
+    Ok(CachedJar {
+        path: object_path,
+        file_name,
+        checksum,
+    })
+}
+
+// yields (platform, version, build file) for every file in the jars directory
+fn for_each_entry(mut f: impl FnMut(String, String, PathBuf) -> Result<()>) -> Result<()> {
+    let jars_dir = get_cache_dir()?.join(JARS_DIRECTORY);
+
+    if !jars_dir.is_dir() {
+        return Ok(());
+    }
+
+    for platform in fs::read_dir(jars_dir)? {
+        let platform = platform?;
+
+        for version in fs::read_dir(platform.path())? {
+            let version = version?;
+
+            for build in fs::read_dir(version.path())? {
+                f(
+                    platform.file_name().to_string_lossy().to_string(),
+                    version.file_name().to_string_lossy().to_string(),
+                    build?.path(),
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn list() -> Result<()> {
+    let mut lines = vec![];
+
+    for_each_entry(|platform, version, path| {
+        let build = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        // partial downloads used to be stored next to the entries
+        let line = if is_partial(&path) {
+            return Ok(());
+        } else if let Some(entry) = read_entry(&path)? {
+            let size = fs::metadata(get_object_path(&entry.sha256)?)
+                .map_or("missing".to_string(), |metadata| {
+                    progress::format_bytes(metadata.len() as f64)
+                });
+            format!(
+                "{platform} {version} build {build}: {} ({size})",
+                entry.file_name
+            )
+        } else {
+            return Ok(());
+        };
+
+        lines.push(line);
+        Ok(())
+    })?;
+
+    let partial_dir = get_cache_dir()?.join(PARTIAL_DIRECTORY);
+    if partial_dir.is_dir() {
+        for part in fs::read_dir(partial_dir)? {
+            let part_path = part?.path();
+            if !is_partial(&part_path) {
+                continue;
+            }
+
+            let url = read_partial(&part_path.with_extension(ENTRY_EXTENSION))?
+                .map_or("an unknown url".to_string(), |partial| partial.url);
+            lines.push(format!(
+                "partial download of {url} ({})",
+                progress::format_bytes(fs::metadata(&part_path)?.len() as f64)
+            ));
+        }
+    }
+
+    lines.sort();
+    for line in lines {
+        println!("{line}");
+    }
+
+    Ok(())
+}
+
+fn get_used_checksums() -> Result<HashSet<Checksum>> {
+    let servers_dir = get_expanded_servers_dir()?;
+    let mut servers = vec![];
+    server::for_each(|server| servers.push(servers_dir.join(server)))?;
+
+    let mut checksums = HashSet::new();
+    for server_dir in servers {
+        // servers with unreadable metadata shouldn't stop the cache from being cleaned
+        if let Ok(Some(jar_info)) = server::get_jar_info(&server_dir)
+            && let Some(checksum) = jar_info.checksum
+        {
+            checksums.insert(checksum);
+        }
+    }
+
+    Ok(checksums)
+}
+
+// with `unused`, jars that are still recorded in the metadata of a server are kept
+pub fn clean(unused: bool) -> Result<()> {
+    let cache_dir = get_cache_dir()?;
+
+    if !unused {
+        if cache_dir.is_dir() {
+            server::remove_dir_with_retries(&cache_dir)?;
+        }
+        println!("Removed the download cache");
+        return Ok(());
+    }
+
+    let used = get_used_checksums()?;
+    let mut kept_objects = HashSet::new();
+    let mut removed = 0;
+
+    for_each_entry(|_, _, path| {
+        if is_partial(&path) {
+            return Ok(fs::remove_file(&path)?);
+        }
+
+        match read_entry(&path)? {
+            Some(entry) if used.contains(&entry.checksum) => {
+                kept_objects.insert(format!("{}.jar", entry.sha256));
+            }
+            _ => {
+                fs::remove_file(&path)?;
+                removed += 1;
+            }
+        }
+
+        Ok(())
+    })?;
+
+    let partial_dir = cache_dir.join(PARTIAL_DIRECTORY);
+    if partial_dir.is_dir() {
+        server::remove_dir_with_retries(&partial_dir)?;
+    }
+
+    let objects_dir = cache_dir.join(OBJECTS_DIRECTORY);
+    if objects_dir.is_dir() {
+        for object in fs::read_dir(objects_dir)? {
+            let object = object?;
+            if !kept_objects.contains(&*object.file_name().to_string_lossy()) {
+                fs::remove_file(object.path())?;
+            }
+        }
+    }
+
+    println!(
+        "Removed {removed} cached jar{}",
+        if removed == 1 { "" } else { "s" }
+    );
+
+    Ok(())
+}
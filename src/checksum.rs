@@ -0,0 +1,79 @@
+use crate::error::Result;
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, Read, Write},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Md5 => write!(f, "md5"),
+            Self::Sha1 => write!(f, "sha1"),
+            Self::Sha256 => write!(f, "sha256"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct Checksum {
+    pub algorithm: Algorithm,
+    pub hash: String,
+}
+
+impl Checksum {
+    pub fn new(algorithm: Algorithm, hash: impl AsRef<str>) -> Self {
+        Self {
+            algorithm,
+            hash: hash.as_ref().to_lowercase(),
+        }
+    }
+}
+
+impl Display for Checksum {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.hash)
+    }
+}
+
+fn copy_with<D: Digest>(mut reader: impl Read, mut writer: impl Write) -> io::Result<String> {
+    let mut hasher = D::new();
+    let mut buffer = [0; 64 * 1024];
+
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+
+        hasher.update(&buffer[..read]);
+        writer.write_all(&buffer[..read])?;
+    }
+
+    writer.flush()?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+// hashes everything that is copied from the reader to the writer
+pub fn copy(reader: impl Read, writer: impl Write, algorithm: Algorithm) -> Result<Checksum> {
+    let hash = match algorithm {
+        Algorithm::Md5 => copy_with::<Md5>(reader, writer)?,
+        Algorithm::Sha1 => copy_with::<Sha1>(reader, writer)?,
+        Algorithm::Sha256 => copy_with::<Sha256>(reader, writer)?,
+    };
+
+    Ok(Checksum::new(algorithm, hash))
+}
@@ -14,6 +14,35 @@ pub enum Commands {
     #[command(visible_alias = "a", about = "Attach to a server session")]
     Attach { server: Option<String> },
 
+    #[command(
+        visible_alias = "bak",
+        about = "Create, list or restore server backups"
+    )]
+    Backup {
+        #[command(subcommand)]
+        action: BackupCommands,
+    },
+
+    #[command(about = "List or clean the cache of downloaded server jars")]
+    Cache {
+        #[command(subcommand)]
+        action: CacheCommands,
+    },
+
+    #[command(about = "Copy a server, giving the copy its own ports and rcon password")]
+    Clone {
+        src: String,
+        dst: String,
+
+        #[arg(
+            short,
+            long = "exclude",
+            value_name = "PATTERN",
+            help = "Skip files matching a gitignore pattern, in addition to .mcserverignore"
+        )]
+        excludes: Vec<String>,
+    },
+
     #[command(visible_alias = "conf", about = "Query the configuration")]
     Config {
         #[command(subcommand)]
@@ -51,6 +80,19 @@ pub enum Commands {
     #[command(visible_alias = "dpl", about = "Deploy a server")]
     Deploy { server: Option<String> },
 
+    #[command(about = "Show how a server has drifted from the templates it was created from")]
+    Drift {
+        server: Option<String>,
+
+        #[arg(
+            short,
+            long = "apply",
+            value_name = "PATH",
+            help = "Restore a file or directory to its template version instead"
+        )]
+        apply: Vec<String>,
+    },
+
     #[command(visible_alias = "exec", about = "Execute a command on a server")]
     Execute {
         #[arg(short, long)]
@@ -60,6 +102,73 @@ pub enum Commands {
         commands: Vec<String>,
     },
 
+    #[command(about = "Export a server as a portable archive with a start script")]
+    Export {
+        server: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            help = "Defaults to <server>.tar.gz in the current directory"
+        )]
+        output: Option<PathBuf>,
+
+        #[arg(long)]
+        without_world: bool,
+
+        #[arg(
+            long,
+            help = "Exclude logs and crash reports even if .mcserverignore includes them"
+        )]
+        without_logs: bool,
+
+        #[arg(
+            short,
+            long = "exclude",
+            value_name = "PATTERN",
+            help = "Skip files matching a gitignore pattern, in addition to .mcserverignore"
+        )]
+        excludes: Vec<String>,
+    },
+
+    #[command(about = "Import an existing server directory or an exported archive")]
+    Import {
+        path: PathBuf,
+
+        #[arg(short, long)]
+        name: Option<String>,
+
+        #[arg(short, long, help = "Symlink the directory instead of moving it")]
+        link: bool,
+    },
+
+    #[command(
+        about = "Show or change how a server is started",
+        group(ArgGroup::new("strategy").args(["jar", "args_file", "script", "command"]))
+    )]
+    Launch {
+        server: Option<String>,
+
+        #[arg(long, help = "Run the server jar with java -jar")]
+        jar: bool,
+
+        #[arg(long, value_name = "PATH", help = "Run java with an args file")]
+        args_file: Option<String>,
+
+        #[arg(long, value_name = "PATH", help = "Run a wrapper script")]
+        script: Option<String>,
+
+        #[arg(
+            long,
+            value_name = "TEMPLATE",
+            help = "Run a shell command with {java}, {jar} and {memory} placeholders"
+        )]
+        command: Option<String>,
+
+        #[arg(short, long, requires = "command", help = "The value of {memory}")]
+        memory: Option<String>,
+    },
+
     #[command(visible_alias = "ls", about = "List all, active or inactive servers")]
     List {
         #[arg(short, long, conflicts_with_all = ["inactive", "dead"])]
@@ -72,6 +181,29 @@ pub enum Commands {
         dead: bool,
     },
 
+    #[command(about = "Check all servers for newer builds of their platform")]
+    Outdated {
+        #[arg(short, long, help = "Update every outdated server to the latest build")]
+        update: bool,
+
+        #[arg(short, long, help = "Back up each server before updating it")]
+        backup: bool,
+    },
+
+    #[command(about = "List the built-in platforms and the custom providers from the config")]
+    Platforms,
+
+    #[command(about = "Query or edit a server's server.properties")]
+    Props {
+        server: Option<String>,
+
+        #[arg(short, long, help = "Minecraft version to validate properties against")]
+        game_version: Option<String>,
+
+        #[command(subcommand)]
+        action: PropsCommands,
+    },
+
     #[command(about = "Interact with a server, using the minecraft remote console")]
     Rcon {
         server: Option<String>,
@@ -79,9 +211,18 @@ pub enum Commands {
         commands: Vec<String>,
     },
 
+    #[command(
+        visible_alias = "net",
+        about = "Declare, configure and start proxy networks"
+    )]
+    Network {
+        #[command(subcommand)]
+        action: NetworkCommands,
+    },
+
     #[command(about = "Create a new server")]
     New {
-        #[clap(value_enum)]
+        #[arg(help = "A built-in platform or a custom provider, see `mcserver platforms`")]
         platform: Platform,
 
         #[arg(short, long)]
@@ -89,6 +230,12 @@ pub enum Commands {
 
         #[arg(short, long)]
         version: Option<String>,
+
+        #[arg(long, help = "Accept the Minecraft EULA without prompting")]
+        accept_eula: bool,
+
+        #[arg(long, help = "Run the server once to generate its default files")]
+        init: bool,
     },
 
     #[command(visible_alias = "reinst", about = "Reinstall the server binary",
@@ -120,13 +267,19 @@ pub enum Commands {
         servers: Vec<String>,
     },
 
+    #[command(visible_alias = "mv", about = "Rename an inactive server")]
+    Rename { old: String, new: String },
+
     #[command(visible_alias = "rst", about = "Restart the current server")]
     Restart,
 
+    #[command(about = "Return a server to the jar it used before its last update")]
+    Rollback { server: Option<String> },
+
     #[command(about = "Stop a server")]
     Stop { server: Option<String> },
 
-    #[command(visible_alias = "tmpl", about = "Create or use a template server")]
+    #[command(visible_alias = "tmpl", about = "Create, use or manage templates")]
     Template {
         #[command(subcommand)]
         action: TemplateCommands,
@@ -139,6 +292,53 @@ pub enum Commands {
         platform: Platform,
 
         version: Option<String>,
+
+        #[arg(short, long, help = "Back up the server before updating")]
+        backup: bool,
+    },
+
+    #[command(about = "List the versions of a platform, or the builds of one of its versions")]
+    Versions {
+        platform: Platform,
+
+        version: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum BackupCommands {
+    #[command(about = "Back up a server and upload it to the configured targets")]
+    Create { server: Option<String> },
+
+    #[command(visible_alias = "ls", about = "List the backups of a server")]
+    List {
+        server: Option<String>,
+
+        #[arg(short, long)]
+        target: Option<String>,
+    },
+
+    #[command(about = "Restore a server from a backup (the latest by default)")]
+    Restore {
+        server: Option<String>,
+
+        #[arg(short, long)]
+        backup: Option<String>,
+
+        #[arg(short, long)]
+        target: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CacheCommands {
+    #[command(visible_alias = "ls", about = "List the cached jars")]
+    List,
+
+    #[command(about = "Remove cached jars")]
+    Clean {
+        #[arg(short, long, help = "Keep the jars that servers are still using")]
+        unused: bool,
     },
 }
 
@@ -157,16 +357,138 @@ pub enum DefaultCommands {
     Set { server: String },
 }
 
+#[derive(Subcommand)]
+pub enum PropsCommands {
+    Get {
+        key: String,
+    },
+
+    Set {
+        key: String,
+
+        value: String,
+
+        #[arg(short, long, help = "Write the value even if it fails validation")]
+        force: bool,
+    },
+
+    #[command(visible_alias = "ls")]
+    List,
+
+    #[command(about = "Compare with another server, or with the defaults if none is given")]
+    Diff {
+        other: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum NetworkCommands {
+    #[command(about = "Declare a proxy and the backend servers behind it")]
+    Create {
+        name: String,
+
+        #[arg(short, long)]
+        proxy: String,
+
+        #[arg(short, long = "backend", value_name = "SERVER", required = true)]
+        backends: Vec<String>,
+    },
+
+    #[command(visible_alias = "ls", about = "List all networks")]
+    List,
+
+    #[command(
+        visible_alias = "rm",
+        about = "Delete a network, leaving its servers untouched"
+    )]
+    Delete { name: String },
+
+    #[command(about = "Register the backends with the proxy and configure forwarding")]
+    Apply { name: String },
+
+    #[command(about = "Start the backends, then the proxy")]
+    Start { name: String },
+
+    #[command(about = "Stop the proxy, then the backends")]
+    Stop { name: String },
+}
+
 #[derive(Subcommand)]
 pub enum TemplateCommands {
     New {
         server: String,
+
+        #[arg(
+            short,
+            long = "exclude",
+            value_name = "PATTERN",
+            help = "Skip files matching a gitignore pattern, in addition to .mcserverignore"
+        )]
+        excludes: Vec<String>,
+    },
+
+    #[command(visible_alias = "ls", about = "List all templates")]
+    List,
+
+    #[command(about = "Show a template's platform, version and variables")]
+    Show { template: String },
+
+    #[command(visible_alias = "rm", about = "Delete a template")]
+    Delete {
+        #[arg(short, long)]
+        force: bool,
+
+        templates: Vec<String>,
+    },
+
+    #[command(
+        visible_alias = "mv",
+        about = "Rename a template, moving legacy templates into the templates directory"
+    )]
+    Rename { old: String, new: String },
+
+    #[command(about = "Replace a template's files with a copy of a server")]
+    UpdateFrom {
+        template: String,
+        server: String,
+
+        #[arg(
+            short,
+            long = "exclude",
+            value_name = "PATTERN",
+            help = "Skip files matching a gitignore pattern, in addition to .mcserverignore"
+        )]
+        excludes: Vec<String>,
     },
 
     From {
+        #[arg(
+            help = "A template name, a .tar.gz or .zip archive, or a git repository written as <url>[#[<ref>][:<subdirectory>]]"
+        )]
         template: String,
 
+        #[arg(
+            help = "Templates or sources layered over the first one, later ones taking precedence"
+        )]
+        overlays: Vec<String>,
+
         #[arg(short, long)]
         server: Option<String>,
+
+        #[arg(
+            long = "set",
+            value_name = "KEY=VALUE",
+            value_parser = parse_assignment,
+            help = "Set a template variable instead of being prompted for it"
+        )]
+        assignments: Vec<(String, String)>,
     },
 }
+
+fn parse_assignment(assignment: &str) -> Result<(String, String), String> {
+    assignment
+        .split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .filter(|(key, _)| !key.is_empty())
+        .ok_or_else(|| format!("expected KEY=VALUE, got `{assignment}`"))
+}
@@ -60,8 +60,11 @@ static CONFIG_DIRECTORY: OnceLock<PathBuf> = OnceLock::new();
 static CONFIG_FILE: OnceLock<PathBuf> = OnceLock::new();
 
 static EXPANDED_SERVERS_DIR: OnceLock<PathBuf> = OnceLock::new();
+static EXPANDED_TEMPLATES_DIR: OnceLock<PathBuf> = OnceLock::new();
 
-fn get_config_directory() -> Result<&'static Path> {
+const JAVA: &str = "java";
+
+pub fn get_config_directory() -> Result<&'static Path> {
     if let Some(path) = CONFIG_DIRECTORY.get() {
         return Ok(path.as_path());
     }
@@ -123,6 +126,37 @@ pub fn get_expanded_servers_dir() -> Result<&'static Path> {
         .as_path())
 }
 
+// servers that don't record the java version they need, or that need an unconfigured one, use
+// java from the path
+pub fn get_java(java_major: Option<u32>) -> Result<String> {
+    let Some(java_major) = java_major else {
+        return Ok(JAVA.to_string());
+    };
+
+    let path = get()?.java_paths.get(&java_major.to_string()).cloned();
+    match path {
+        Some(path) => Ok(shellexpand::full(&path)?.to_string()),
+        None => Ok(JAVA.to_string()),
+    }
+}
+
+pub fn has_java(java_major: u32) -> Result<bool> {
+    Ok(get()?.java_paths.contains_key(&java_major.to_string()))
+}
+
+pub fn get_expanded_templates_dir() -> Result<&'static Path> {
+    if let Some(dir) = EXPANDED_TEMPLATES_DIR.get() {
+        return Ok(dir.as_path());
+    }
+
+    let templates_directory = get()?.templates_directory.clone();
+    let dir = match templates_directory {
+        Some(dir) => PathBuf::from(&*shellexpand::full(&dir)?),
+        None => get_config_directory()?.join("templates"),
+    };
+    Ok(EXPANDED_TEMPLATES_DIR.get_or_init(|| dir).as_path())
+}
+
 pub fn get_current_server_directory() -> Result<String> {
     let servers_dir = get_expanded_servers_dir()?;
     let current_dir = env::current_dir()?;
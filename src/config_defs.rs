@@ -40,11 +40,112 @@ pub struct RconConfig {
     pub password: Option<Password>,
 }
 
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BackupTarget {
+    Local {
+        path: String,
+    },
+    S3 {
+        endpoint: String,
+        bucket: String,
+        region: Option<String>,
+        prefix: Option<String>,
+        access_key: Option<String>,
+        secret_key: Option<Password>,
+    },
+    Sftp {
+        host: String,
+        user: Option<String>,
+        port: Option<u16>,
+        path: String,
+        identity_file: Option<String>,
+    },
+    Rsync {
+        destination: String,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BackupEncryption {
+    pub passphrase_file: Option<String>,
+    pub passphrase_env: Option<String>,
+    #[serde(default)]
+    pub recipients: Vec<String>,
+    pub identity_file: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct BackupConfig {
+    pub directory: Option<String>,
+    pub retention: Option<usize>,
+    pub encryption: Option<BackupEncryption>,
+    #[serde(default)]
+    pub targets: HashMap<String, BackupTarget>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl Default for PortRange {
+    fn default() -> Self {
+        Self {
+            start: 25565,
+            end: 25665,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct NetworkConfig {
+    pub proxy: String,
+    pub backends: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ProviderSource {
+    Template {
+        url: String,
+        #[serde(default)]
+        versions: Vec<String>,
+        checksum_url: Option<String>,
+    },
+    Json {
+        url: String,
+    },
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ProviderConfig {
+    pub source: ProviderSource,
+    #[serde(default)]
+    pub proxy: bool,
+    pub java_major: Option<u32>,
+    pub launch: Option<String>,
+    pub post_install: Option<String>,
+}
+
 #[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct DynamicConfig {
     pub default_java_args: String,
+    #[serde(default)]
+    pub java_paths: HashMap<String, String>,
     pub nogui: bool,
     pub servers_directory: String,
+    #[serde(default)]
+    pub templates_directory: Option<String>,
     pub default_server: String,
+    #[serde(default)]
+    pub port_range: PortRange,
     pub rcon: HashMap<String, RconConfig>,
+    #[serde(default)]
+    pub backup: BackupConfig,
+    #[serde(default)]
+    pub networks: HashMap<String, NetworkConfig>,
+    #[serde(default)]
+    pub providers: HashMap<String, ProviderConfig>,
 }
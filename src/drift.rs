@@ -0,0 +1,330 @@
+use crate::{
+    checksum::{self, Algorithm},
+    error::{Error, Result},
+    overlay::{self, Format},
+    properties::Properties,
+    server::{self, METADATA_DIRECTORY},
+    session, source, template,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+pub const BASELINE_FILE: &str = "baseline.toml";
+pub const BASELINE_DIRECTORY: &str = "baseline";
+
+// the state of a server right after it was created from its templates, with copies of its text
+// configs so that they can be compared and restored
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Baseline {
+    files: BTreeMap<String, String>,
+}
+
+fn hash_file(path: &Path) -> Result<String> {
+    Ok(checksum::copy(File::open(path)?, io::sink(), Algorithm::Sha256)?.hash)
+}
+
+// relative paths always use `/`, so that baselines stay comparable across platforms
+fn collect_files(dir: &Path, relative: &str, files: &mut BTreeMap<String, PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let relative = if relative.is_empty() {
+            name
+        } else {
+            format!("{relative}/{name}")
+        };
+
+        if relative == METADATA_DIRECTORY {
+            continue;
+        }
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(&entry.path(), &relative, files)?;
+        } else if file_type.is_file() {
+            files.insert(relative, entry.path());
+        }
+    }
+
+    Ok(())
+}
+
+pub fn record(server_dir: impl AsRef<Path>) -> Result<()> {
+    let server_dir = server_dir.as_ref();
+    let metadata_dir = server_dir.join(METADATA_DIRECTORY);
+    let copies_dir = metadata_dir.join(BASELINE_DIRECTORY);
+
+    if copies_dir.exists() {
+        server::remove_dir_with_retries(&copies_dir)?;
+    }
+
+    let mut files = BTreeMap::new();
+    collect_files(server_dir, "", &mut files)?;
+
+    let mut baseline = Baseline::default();
+    for (relative, path) in files {
+        if template::is_text_config(&path) {
+            let copy = copies_dir.join(&relative);
+            fs::create_dir_all(copy.parent().unwrap_or(&copies_dir))?;
+            fs::copy(&path, copy)?;
+        }
+
+        baseline.files.insert(relative, hash_file(&path)?);
+    }
+
+    fs::create_dir_all(&metadata_dir)?;
+    fs::write(
+        metadata_dir.join(BASELINE_FILE),
+        toml::to_string(&baseline)?,
+    )?;
+
+    Ok(())
+}
+
+fn read_baseline(server: &str, metadata_dir: &Path) -> Result<Baseline> {
+    let path = metadata_dir.join(BASELINE_FILE);
+
+    if !path.is_file() {
+        return Err(Error::NoBaseline(server.to_string()));
+    }
+
+    Ok(toml::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn flatten_toml(prefix: &str, table: toml::Table, values: &mut BTreeMap<String, String>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key
+        } else {
+            format!("{prefix}.{key}")
+        };
+
+        match value {
+            toml::Value::Table(table) => flatten_toml(&key, table, values),
+            value => {
+                values.insert(key, value.to_string());
+            }
+        }
+    }
+}
+
+fn flatten_yaml(prefix: &str, value: serde_norway::Value, values: &mut BTreeMap<String, String>) {
+    match value {
+        serde_norway::Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                let key = match key {
+                    serde_norway::Value::String(key) => key,
+                    key => serde_json::to_string(&key).unwrap_or_default(),
+                };
+                let key = if prefix.is_empty() {
+                    key
+                } else {
+                    format!("{prefix}.{key}")
+                };
+
+                flatten_yaml(&key, value, values);
+            }
+        }
+        serde_norway::Value::String(value) => {
+            values.insert(prefix.to_string(), value);
+        }
+        value => {
+            values.insert(
+                prefix.to_string(),
+                serde_json::to_string(&value).unwrap_or_default(),
+            );
+        }
+    }
+}
+
+fn flatten(format: Format, contents: &str) -> Option<BTreeMap<String, String>> {
+    let mut values = BTreeMap::new();
+
+    match format {
+        Format::Properties => {
+            for (key, value) in Properties::parse(contents).iter() {
+                values.insert(key.to_string(), value.to_string());
+            }
+        }
+        Format::Toml => flatten_toml("", contents.parse().ok()?, &mut values),
+        Format::Yaml => flatten_yaml("", serde_norway::from_str(contents).ok()?, &mut values),
+    }
+
+    Some(values)
+}
+
+fn print_structured_diff(baseline_copy: &Path, path: &Path) -> Result<()> {
+    let Some(format) = overlay::get_format(path) else {
+        return Ok(());
+    };
+    if !baseline_copy.is_file() {
+        return Ok(());
+    }
+
+    let (Some(old), Some(new)) = (
+        flatten(format, &fs::read_to_string(baseline_copy)?),
+        flatten(format, &fs::read_to_string(path)?),
+    ) else {
+        return Ok(());
+    };
+
+    for (key, value) in &old {
+        match new.get(key) {
+            Some(current) if current != value => println!("    ~ {key}: {value} -> {current}"),
+            Some(_) => {}
+            None => println!("    - {key}={value}"),
+        }
+    }
+
+    for (key, value) in &new {
+        if !old.contains_key(key) {
+            println!("    + {key}={value}");
+        }
+    }
+
+    Ok(())
+}
+
+// directories that didn't exist in the baseline, such as the world, are shown as a whole
+fn get_added_root(relative: &str, baseline: &Baseline) -> String {
+    let mut end = 0;
+
+    while let Some(offset) = relative[end..].find('/') {
+        end += offset;
+        let prefix = &relative[..=end];
+        if !baseline.files.keys().any(|file| file.starts_with(prefix)) {
+            return prefix.to_string();
+        }
+        end += 1;
+    }
+
+    relative.to_string()
+}
+
+fn show(server: &str, server_dir: &Path, baseline: &Baseline) -> Result<()> {
+    let copies_dir = server_dir.join(METADATA_DIRECTORY).join(BASELINE_DIRECTORY);
+
+    let mut files = BTreeMap::new();
+    collect_files(server_dir, "", &mut files)?;
+
+    let added: BTreeSet<_> = files
+        .keys()
+        .filter(|relative| !baseline.files.contains_key(*relative))
+        .map(|relative| get_added_root(relative, baseline))
+        .collect();
+
+    let mut drifted = !added.is_empty();
+    for (relative, hash) in &baseline.files {
+        match files.get(relative) {
+            None => {
+                println!("- {relative}");
+                drifted = true;
+            }
+            Some(path) if hash_file(path)? != *hash => {
+                println!("~ {relative}");
+                print_structured_diff(&copies_dir.join(relative), path)?;
+                drifted = true;
+            }
+            Some(_) => {}
+        }
+    }
+
+    for relative in added {
+        println!("+ {relative}");
+    }
+
+    if !drifted {
+        println!("{server} has not drifted from its template");
+    }
+
+    Ok(())
+}
+
+// text configs come from the baseline copies, everything else from a layer that still has
+// the same contents
+fn find_original(server_dir: &Path, relative: &str, hash: &str) -> Result<Option<PathBuf>> {
+    let copy = server_dir
+        .join(METADATA_DIRECTORY)
+        .join(BASELINE_DIRECTORY)
+        .join(relative);
+    if copy.is_file() {
+        return Ok(Some(copy));
+    }
+
+    for origin in source::read(server_dir)?
+        .unwrap_or_default()
+        .into_iter()
+        .rev()
+    {
+        let Some(path) = origin.dir.map(|dir| dir.join(relative)) else {
+            continue;
+        };
+
+        if path.is_file() && hash_file(&path)? == hash {
+            return Ok(Some(path));
+        }
+    }
+
+    Ok(None)
+}
+
+fn apply(server: &str, server_dir: &Path, baseline: &Baseline, paths: Vec<String>) -> Result<()> {
+    let mut selected = BTreeMap::new();
+
+    for path in paths {
+        let path = path.trim_start_matches("./").trim_end_matches('/');
+        let prefix = format!("{path}/");
+        let len = selected.len();
+
+        selected.extend(
+            baseline
+                .files
+                .iter()
+                .filter(|(file, _)| *file == path || file.starts_with(&prefix)),
+        );
+
+        if selected.len() == len {
+            return Err(Error::NotTemplateFile(path.to_string()));
+        }
+    }
+
+    let mut originals = vec![];
+    for (relative, hash) in selected {
+        let original = find_original(server_dir, relative, hash)?
+            .ok_or_else(|| Error::TemplateFileUnavailable(relative.clone()))?;
+        originals.push((relative, original));
+    }
+
+    for (relative, original) in originals {
+        let path = server_dir.join(relative);
+        fs::create_dir_all(path.parent().unwrap_or(server_dir))?;
+        if path.exists() {
+            fs::remove_file(&path)?;
+        }
+        fs::copy(original, path)?;
+        println!("Restored {relative}");
+    }
+
+    if session::is_active(server)? {
+        eprintln!("Warning: {server} is running and must be restarted for the changes to apply");
+    }
+
+    Ok(())
+}
+
+pub fn run(server: impl AsRef<str>, paths: Vec<String>) -> Result<()> {
+    let server = server.as_ref();
+    let server_dir = server::get_server_dir_required(server)?;
+    let baseline = read_baseline(server, &server_dir.join(METADATA_DIRECTORY))?;
+
+    if paths.is_empty() {
+        show(server, &server_dir, &baseline)
+    } else {
+        apply(server, &server_dir, &baseline, paths)
+    }
+}
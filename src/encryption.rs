@@ -0,0 +1,71 @@
+use crate::{
+    config_defs::BackupEncryption,
+    error::{Error, Result},
+};
+use age::{
+    Decryptor, Encryptor, Identity, IdentityFile, Recipient, secrecy::SecretString,
+    stream::StreamReader, x25519,
+};
+use std::{env, fs, io::Read, iter};
+
+pub const EXTENSION: &str = ".age";
+
+fn get_passphrase(encryption: &BackupEncryption) -> Result<SecretString> {
+    if let Some(file) = &encryption.passphrase_file {
+        let passphrase = fs::read_to_string(&*shellexpand::full(file)?)?;
+        return Ok(SecretString::from(
+            passphrase.trim_end_matches(['\r', '\n']),
+        ));
+    }
+
+    encryption
+        .passphrase_env
+        .as_ref()
+        .and_then(|var| env::var(var).ok())
+        .map(SecretString::from)
+        .ok_or_else(|| Error::MissingCredentials("backup passphrase".to_string()))
+}
+
+pub fn encryptor(encryption: &BackupEncryption) -> Result<Encryptor> {
+    if encryption.recipients.is_empty() {
+        return Ok(Encryptor::with_user_passphrase(get_passphrase(encryption)?));
+    }
+
+    let recipients = encryption
+        .recipients
+        .iter()
+        .map(|recipient| {
+            recipient
+                .parse::<x25519::Recipient>()
+                .map_err(|reason| Error::InvalidRecipient {
+                    recipient: recipient.clone(),
+                    reason: reason.to_string(),
+                })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Encryptor::with_recipients(
+        recipients
+            .iter()
+            .map(|recipient| recipient as &dyn Recipient),
+    )?)
+}
+
+pub fn decrypt<R: Read>(encryption: &BackupEncryption, input: R) -> Result<StreamReader<R>> {
+    let decryptor = Decryptor::new(input)?;
+
+    if decryptor.is_scrypt() {
+        let identity = age::scrypt::Identity::new(get_passphrase(encryption)?);
+        return Ok(decryptor.decrypt(iter::once(&identity as &dyn Identity))?);
+    }
+
+    let identity_file = encryption
+        .identity_file
+        .as_ref()
+        .ok_or_else(|| Error::MissingCredentials("backup identity file".to_string()))?;
+
+    let identities = IdentityFile::from_file(shellexpand::full(identity_file)?.to_string())?
+        .into_identities()?;
+
+    Ok(decryptor.decrypt(identities.iter().map(|identity| identity.as_ref()))?)
+}
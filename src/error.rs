@@ -10,6 +10,22 @@ use thiserror::Error;
 #[non_exhaustive]
 #[derive(Debug, Error)]
 pub enum Error {
+    #[error("Backup {0} was not found")]
+    BackupNotFound(String),
+
+    #[error("Backup target {0} was not found")]
+    BackupTargetNotFound(String),
+
+    #[error("Failed to upload backup to: {0}")]
+    BackupUploadFailed(String),
+
+    #[error("Checksum mismatch for {file}: expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+
     #[error(
         "Command failed with code {}{}",
         code.map(|c| c.to_string()).as_deref().unwrap_or("none"),
@@ -24,9 +40,37 @@ pub enum Error {
         stderr: Option<Vec<u8>>,
     },
 
+    #[error(transparent)]
+    Decrypt(#[from] age::DecryptError),
+
+    #[error(transparent)]
+    Encrypt(#[from] age::EncryptError),
+
+    #[error(transparent)]
+    Ignore(#[from] ignore::Error),
+
+    #[error("The installer failed, see {} for details", log.display())]
+    InstallerFailed { log: PathBuf },
+
+    #[error("{} does not contain a single server directory", file.display())]
+    InvalidArchive { file: PathBuf },
+
     #[error(transparent)]
     InvalidHeaderValue(#[from] header::InvalidHeaderValue),
 
+    #[error("Invalid encryption recipient {recipient}: {reason}")]
+    InvalidRecipient { recipient: String, reason: String },
+
+    #[error("Invalid value `{value}` for {key}, expected {expected}")]
+    InvalidPropertyValue {
+        key: String,
+        value: String,
+        expected: String,
+    },
+
+    #[error("Invalid server name: `{0}`")]
+    InvalidServerName(String),
+
     #[error("Invalid server session: `{0}`")]
     InvalidServerSession(String),
 
@@ -39,30 +83,79 @@ pub enum Error {
     #[error(transparent)]
     Io(#[from] io::Error),
 
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
     #[error("Missing directory: {}", dir.display())]
     MissingDirectory { dir: PathBuf },
 
+    #[error("Missing credentials: {0}")]
+    MissingCredentials(String),
+
     #[error("Missing file: {}", file.display())]
     MissingFile { file: PathBuf },
 
+    #[error("Network {0} already exists")]
+    NetworkAlreadyExists(String),
+
+    #[error("Network with the name {0} was not found")]
+    NetworkNotFound(String),
+
+    #[error("No value was given for the template variable {0}")]
+    MissingTemplateVariable(String),
+
+    #[error("No free ports are left in the range {start}-{end}")]
+    NoFreePorts { start: u16, end: u16 },
+
+    #[error("{0} was not created from a template")]
+    NoBaseline(String),
+
+    #[error("No revisions were recorded for {0}")]
+    NoRevisions(String),
+
+    #[error("No server jar was found in {}", dir.display())]
+    NoServerJar { dir: PathBuf },
+
     #[error("No server child was given")]
     NoServerChild,
 
     #[error("No session name found")]
     NoSessionName,
 
+    #[error("{0} is not a velocity or bungeecord proxy")]
+    NotProxy(String),
+
+    #[error("{0} did not come from the server's template")]
+    NotTemplateFile(String),
+
     #[error("Platforms not found: {0}")]
     PlatformsNotFound(String),
 
     #[error("The configuration mutex has been poisoned")]
     ConfigMutexPoisoned,
 
+    #[error("Port {port} ({key}) is already in use by active server {server}")]
+    PortConflict {
+        server: String,
+        key: String,
+        port: u16,
+    },
+
+    #[error("Property {0} is not set")]
+    PropertyNotFound(String),
+
     #[error("Rcon config is missing for server: {0}")]
     MissingRconConfig(String),
 
     #[error(transparent)]
     Reqwest(#[from] reqwest::Error),
 
+    #[error("S3 request failed with status {status}: {message}")]
+    S3Request { status: u16, message: String },
+
+    #[error("Server {0} is active")]
+    ServerActive(String),
+
     #[error(transparent)]
     ShellexpandLookup(#[from] shellexpand::LookupError<VarError>),
 
@@ -81,6 +174,12 @@ pub enum Error {
     #[error("Template {0} already exists")]
     TemplateAlreadyExists(String),
 
+    #[error("The template version of {0} is no longer available")]
+    TemplateFileUnavailable(String),
+
+    #[error("Templates cannot be attached to")]
+    TemplateAttached,
+
     #[error("Template servers cannot be deployed")]
     TemplateDeployed,
 
@@ -93,14 +192,45 @@ pub enum Error {
     #[error(transparent)]
     TomlDeserialize(#[from] toml::de::Error),
 
+    #[error(transparent)]
+    TomlEdit(#[from] toml_edit::TomlError),
+
     #[error(transparent)]
     TomlSerialize(#[from] toml::ser::Error),
 
     #[error(transparent)]
     ToStr(#[from] header::ToStrError),
 
+    #[error("Couldn't read a checksum from {0}")]
+    UnknownChecksum(String),
+
+    #[error("Unknown platform {0}, custom platforms are configured under providers")]
+    UnknownPlatform(String),
+
+    #[error("Unknown property {key}{}", suggestion.as_ref().map(|s| format!(" (did you mean {s}?)")).unwrap_or_default())]
+    UnknownProperty {
+        key: String,
+        suggestion: Option<String>,
+    },
+
+    #[error("Property {key} requires Minecraft {since} (server is on {version})")]
+    UnsupportedProperty {
+        key: String,
+        since: String,
+        version: String,
+    },
+
+    #[error("Failed to update: {0}")]
+    UpdateFailed(String),
+
     #[error(transparent)]
     UrlParse(#[from] url::ParseError),
+
+    #[error(transparent)]
+    Yaml(#[from] serde_norway::Error),
+
+    #[error(transparent)]
+    Zip(#[from] zip::result::ZipError),
 }
 
 pub type Result<T> = result::Result<T, Error>;
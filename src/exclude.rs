@@ -0,0 +1,79 @@
+use crate::{
+    error::Result,
+    revision::{REVISIONS_DIRECTORY, STAGING_DIRECTORY},
+    server::METADATA_DIRECTORY,
+};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::{fs, path::Path};
+
+pub const FILE_NAME: &str = ".mcserverignore";
+
+const DEFAULT_PATTERNS: [&str; 2] = ["/logs/", "/crash-reports/"];
+
+pub struct Excludes(Gitignore);
+
+impl Excludes {
+    // later patterns win, so `.mcserverignore` can re-include defaults with `!` and
+    // patterns given on the command line override both
+    pub fn new(src_dir: impl AsRef<Path>, patterns: &[String]) -> Result<Self> {
+        let src_dir = src_dir.as_ref();
+        let mut builder = GitignoreBuilder::new(src_dir);
+
+        for pattern in DEFAULT_PATTERNS {
+            builder.add_line(None, pattern)?;
+        }
+
+        // previous jars are only useful on the machine that recorded them
+        for dir in [REVISIONS_DIRECTORY, STAGING_DIRECTORY] {
+            builder.add_line(None, &format!("/{METADATA_DIRECTORY}/{dir}/"))?;
+        }
+
+        let ignore_file = src_dir.join(FILE_NAME);
+        if ignore_file.is_file()
+            && let Some(err) = builder.add(ignore_file)
+        {
+            return Err(err.into());
+        }
+
+        for pattern in patterns {
+            builder.add_line(None, pattern)?;
+        }
+
+        Ok(Self(builder.build()?))
+    }
+
+    // paths are relative to the source directory
+    pub fn is_excluded(&self, path: impl AsRef<Path>, is_dir: bool) -> bool {
+        self.0.matched(path, is_dir).is_ignore()
+    }
+}
+
+pub fn copy_directory(
+    src: impl AsRef<Path>,
+    dst: impl AsRef<Path>,
+    excludes: &Excludes,
+) -> Result<()> {
+    copy_children(src.as_ref(), dst.as_ref(), Path::new(""), excludes)
+}
+
+fn copy_children(src: &Path, dst: &Path, relative: &Path, excludes: &Excludes) -> Result<()> {
+    fs::create_dir_all(dst.join(relative))?;
+
+    for entry in fs::read_dir(src.join(relative))? {
+        let entry = entry?;
+        let relative = relative.join(entry.file_name());
+        let is_dir = entry.file_type()?.is_dir();
+
+        if excludes.is_excluded(&relative, is_dir) {
+            continue;
+        }
+
+        if is_dir {
+            copy_children(src, dst, &relative, excludes)?;
+        } else {
+            fs::copy(entry.path(), dst.join(&relative))?;
+        }
+    }
+
+    Ok(())
+}
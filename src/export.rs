@@ -0,0 +1,116 @@
+use crate::{
+    archive, config,
+    error::Result,
+    exclude::Excludes,
+    launch,
+    properties::{self, Properties},
+    server, session,
+};
+use std::{
+    env,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+const START_SCRIPT: &str = "start.sh";
+const LOG_DIRECTORIES: [&str; 2] = ["logs", "crash-reports"];
+
+fn get_world_directories(server_dir: &Path) -> Result<Vec<String>> {
+    let path = server_dir.join(properties::FILE_NAME);
+    let level_name = if path.is_file() {
+        Properties::read(path)?.get("level-name").map(String::from)
+    } else {
+        None
+    }
+    .unwrap_or_else(|| "world".to_string());
+
+    Ok(vec![
+        format!("{level_name}_nether"),
+        format!("{level_name}_the_end"),
+        level_name,
+    ])
+}
+
+fn start_script(server_dir: &Path) -> Result<String> {
+    let nogui = config::get()?.nogui;
+    let command = launch::read(server_dir)?.command_line(server_dir, nogui)?;
+
+    Ok(format!(
+        "#!/bin/sh\ncd \"$(dirname \"$0\")\" || exit 1\nexec {command} \"$@\"\n"
+    ))
+}
+
+pub fn export(
+    server: impl AsRef<str>,
+    output: Option<PathBuf>,
+    without_world: bool,
+    without_logs: bool,
+    mut excludes: Vec<String>,
+) -> Result<()> {
+    let server = server.as_ref();
+    let server_dir = server::get_server_dir_required(server)?;
+
+    if session::is_active(server)? {
+        eprintln!("Warning: {server} is running, the exported files may be inconsistent");
+    }
+
+    if without_world {
+        excludes.extend(
+            get_world_directories(&server_dir)?
+                .into_iter()
+                .map(|dir| format!("/{dir}/")),
+        );
+    }
+    // logs are excluded by default, but `.mcserverignore` may include them again
+    if without_logs {
+        excludes.extend(LOG_DIRECTORIES.map(|dir| format!("/{dir}/")));
+    }
+    let excludes = Excludes::new(&server_dir, &excludes)?;
+
+    let script = start_script(&server_dir)?;
+    let output = env::current_dir()?.join(match output {
+        Some(output) => output,
+        None => PathBuf::from(format!("{server}{}", archive::EXTENSION)),
+    });
+
+    // an archive written inside the server would otherwise end up in itself
+    let output_in_server = output
+        .parent()
+        .and_then(|parent| fs::canonicalize(parent).ok())
+        .zip(output.file_name())
+        .and_then(|(parent, file_name)| {
+            parent
+                .join(file_name)
+                .strip_prefix(fs::canonicalize(&server_dir).ok()?)
+                .map(Path::to_path_buf)
+                .ok()
+        });
+
+    let write = || -> Result<()> {
+        let mut writer = archive::Writer::new(BufWriter::new(File::create(&output)?));
+
+        writer.append_dir_filtered(&server_dir, server, |path| {
+            path == Path::new(START_SCRIPT)
+                || Some(path) == output_in_server.as_deref()
+                || excludes.is_excluded(path, server_dir.join(path).is_dir())
+        })?;
+        writer.append_file(
+            Path::new(server).join(START_SCRIPT),
+            script.as_bytes(),
+            0o755,
+        )?;
+
+        writer.finish()?.flush()?;
+        Ok(())
+    };
+
+    if let Err(err) = write() {
+        let _ = fs::remove_file(&output);
+        return Err(err);
+    }
+
+    println!("Exported {server} to {}", output.display());
+
+    Ok(())
+}
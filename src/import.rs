@@ -0,0 +1,350 @@
+use crate::{
+    archive,
+    config::get_expanded_servers_dir,
+    error::{Error, Result},
+    platforms::Platform,
+    ports,
+    server::{self, JarInfo, METADATA_DIRECTORY},
+};
+use serde::Deserialize;
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    os::unix,
+    path::{Path, PathBuf},
+    process,
+};
+use zip::ZipArchive;
+
+const FABRIC_LAUNCHER_PROPERTIES: &str = "fabric-server-launcher.properties";
+const PAPER_VERSION_HISTORY: &str = "version_history.json";
+
+#[derive(Debug, Deserialize)]
+struct VersionJson {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionHistory {
+    #[serde(rename = "currentVersion")]
+    current_version: String,
+}
+
+struct JarCandidate {
+    file_name: String,
+    main_class: Option<String>,
+    version: Option<String>,
+}
+
+fn read_zip_entry(archive: &mut ZipArchive<File>, name: &str) -> Option<String> {
+    let mut entry = archive.by_name(name).ok()?;
+    let mut contents = String::new();
+    entry.read_to_string(&mut contents).ok()?;
+    Some(contents)
+}
+
+fn inspect_jar(path: &Path) -> Option<JarCandidate> {
+    let mut archive = ZipArchive::new(File::open(path).ok()?).ok()?;
+
+    let main_class = read_zip_entry(&mut archive, "META-INF/MANIFEST.MF").and_then(|manifest| {
+        manifest
+            .lines()
+            .find_map(|line| line.strip_prefix("Main-Class:"))
+            .map(|class| class.trim().to_string())
+    });
+
+    let version = read_zip_entry(&mut archive, "version.json")
+        .and_then(|json| serde_json::from_str::<VersionJson>(&json).ok())
+        .map(|version| version.id);
+
+    Some(JarCandidate {
+        file_name: path.file_name()?.to_string_lossy().to_string(),
+        main_class,
+        version,
+    })
+}
+
+fn platform_from_main_class(main_class: &str) -> Option<Platform> {
+    if main_class.starts_with("io.papermc.paperclip") {
+        Some(Platform::Paper)
+    } else if main_class.starts_with("net.fabricmc") {
+        Some(Platform::Fabric)
+    } else if main_class.starts_with("net.minecraftforge") {
+        Some(Platform::Forge)
+    } else if main_class.starts_with("net.neoforged") {
+        Some(Platform::Neoforge)
+    } else if main_class.starts_with("com.velocitypowered") {
+        Some(Platform::Velocity)
+    } else if main_class.starts_with("net.md_5.bungee") {
+        Some(Platform::Bungeecord)
+    } else if main_class.starts_with("org.quiltmc") {
+        Some(Platform::Quilt)
+    } else if main_class.starts_with("org.bukkit.craftbukkit") {
+        Some(Platform::Spigot)
+    } else if main_class.starts_with("net.minecraft.server")
+        || main_class.starts_with("net.minecraft.bundler")
+    {
+        Some(Platform::Vanilla)
+    } else {
+        None
+    }
+}
+
+fn is_installer(candidate: &JarCandidate) -> bool {
+    candidate.file_name.contains("-installer")
+        || candidate.main_class.as_deref().is_some_and(|main_class| {
+            main_class.ends_with("installer.Main") || main_class.ends_with("SimpleInstaller")
+        })
+}
+
+fn is_server_main_class(main_class: &str) -> bool {
+    platform_from_main_class(main_class).is_some()
+        || main_class.starts_with("net.minecraft")
+        || main_class.starts_with("org.bukkit")
+}
+
+fn platform_from_name(name: &str) -> Option<Platform> {
+    let name = name.to_lowercase();
+
+    [
+        Platform::Purpur,
+        Platform::Folia,
+        Platform::Waterfall,
+        Platform::Velocity,
+        Platform::Bungeecord,
+        Platform::Paper,
+        Platform::Spigot,
+        Platform::Fabric,
+        Platform::Quilt,
+        Platform::Neoforge,
+        Platform::Forge,
+        Platform::Vanilla,
+    ]
+    .into_iter()
+    .find(|platform| name.contains(&platform.to_string()))
+}
+
+// parses values such as `git-Paper-196 (MC: 1.20.1)`
+fn parse_version_history(server_dir: &Path) -> Option<JarInfo> {
+    let contents = fs::read_to_string(server_dir.join(PAPER_VERSION_HISTORY)).ok()?;
+    let current = serde_json::from_str::<VersionHistory>(&contents)
+        .ok()?
+        .current_version;
+
+    let version = current
+        .split("(MC: ")
+        .nth(1)
+        .and_then(|rest| rest.split(')').next())
+        .map(String::from);
+
+    let mut parts = current.split_whitespace().next()?.split('-');
+    let build = match (parts.next(), parts.next(), parts.next()) {
+        (Some("git"), Some(_), Some(build)) => Some(build.to_string()),
+        (Some(_), Some(build), _) => Some(build.to_string()),
+        _ => None,
+    };
+
+    Some(JarInfo {
+        platform: platform_from_name(&current),
+        version,
+        build,
+        checksum: None,
+        java_major: None,
+    })
+}
+
+// reads the minecraft version out of names like `fabric-server-mc.1.20.1-loader.0.15.0-launcher.1.0.0.jar`
+// or `paper-1.20.1-196.jar`
+fn parse_jar_name(name: &str) -> (Option<String>, Option<String>) {
+    let stem = name.strip_suffix(".jar").unwrap_or(name);
+
+    if let Some(rest) = stem.split("-mc.").nth(1) {
+        return (rest.split('-').next().map(String::from), None);
+    }
+
+    let mut parts = stem.split('-').skip(1);
+    let version = parts
+        .next()
+        .filter(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+        .map(String::from);
+    let build = version
+        .as_ref()
+        .and(parts.next())
+        .filter(|build| build.chars().all(|c| c.is_ascii_digit()))
+        .map(String::from);
+
+    (version, build)
+}
+
+pub fn detect(server_dir: impl AsRef<Path>) -> Result<(String, JarInfo)> {
+    let server_dir = server_dir.as_ref();
+
+    let mut candidates = vec![];
+    for entry in fs::read_dir(server_dir)? {
+        let path = entry?.path();
+        if path.is_file() && path.extension().is_some_and(|ext| ext == "jar") {
+            candidates.extend(inspect_jar(&path));
+        }
+    }
+
+    // fabric's launcher downloads the vanilla jar next to itself, so it must be preferred
+    let is_fabric = server_dir.join(FABRIC_LAUNCHER_PROPERTIES).is_file();
+
+    candidates.sort_by_key(|candidate| {
+        let main_class = candidate.main_class.as_deref().unwrap_or_default();
+        (
+            is_installer(candidate) || !is_server_main_class(main_class),
+            is_fabric && !main_class.starts_with("net.fabricmc"),
+            platform_from_main_class(main_class).is_none(),
+        )
+    });
+
+    let Some(jar) = candidates
+        .into_iter()
+        .find(|candidate| candidate.main_class.is_some())
+    else {
+        return Err(Error::NoServerJar {
+            dir: server_dir.to_path_buf(),
+        });
+    };
+
+    let history = parse_version_history(server_dir).unwrap_or_default();
+    let (name_version, name_build) = parse_jar_name(&jar.file_name);
+
+    let platform = history
+        .platform
+        .or_else(|| platform_from_name(&jar.file_name))
+        .or_else(|| jar.main_class.as_deref().and_then(platform_from_main_class))
+        .or(is_fabric.then_some(Platform::Fabric));
+
+    let jar_info = JarInfo {
+        platform,
+        version: history.version.or(jar.version).or(name_version),
+        build: history.build.or(name_build),
+        checksum: None,
+        java_major: None,
+    };
+
+    Ok((jar.file_name, jar_info))
+}
+
+// metadata written by this tool is kept as is, for example when importing an export
+fn get_existing_metadata(server_dir: &Path) -> Result<Option<(String, JarInfo)>> {
+    let Ok(jar_path) = server::get_server_jar_path(server_dir) else {
+        return Ok(None);
+    };
+
+    Ok(server::get_jar_info(server_dir)?.and_then(|jar_info| {
+        jar_path
+            .file_name()
+            .map(|name| (name.to_string_lossy().to_string(), jar_info))
+    }))
+}
+
+fn is_archive(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    name.ends_with(archive::EXTENSION) || name.ends_with(".tgz")
+}
+
+fn import_directory(path: &Path, name: Option<String>, link: bool) -> Result<String> {
+    let name = match name {
+        Some(name) => name,
+        None => path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| Error::MissingDirectory {
+                dir: path.to_path_buf(),
+            })?,
+    };
+    server::validate_name(&name)?;
+
+    let servers_dir = get_expanded_servers_dir()?;
+    let server_dir: PathBuf = servers_dir.join(&name);
+
+    if server_dir.exists() {
+        return Err(Error::ServerAlreadyExists(name));
+    }
+
+    let (jar_file_name, jar_info, detected) = match get_existing_metadata(path)? {
+        Some((jar_file_name, jar_info)) => (jar_file_name, jar_info, false),
+        None => {
+            let (jar_file_name, jar_info) = detect(path)?;
+            (jar_file_name, jar_info, true)
+        }
+    };
+
+    fs::create_dir_all(servers_dir)?;
+    if link {
+        unix::fs::symlink(path, &server_dir)?;
+    } else {
+        server::move_directory(path, &server_dir)?;
+    }
+
+    if detected {
+        let metadata_dir = server_dir.join(METADATA_DIRECTORY);
+        server::set_default_metadata(&metadata_dir, Some(&jar_file_name))?;
+        server::set_jar_info(&metadata_dir, &jar_info)?;
+    }
+
+    println!(
+        "Imported {name} ({}, version {}, jar {jar_file_name})",
+        jar_info
+            .platform
+            .map_or("unknown platform".to_string(), |platform| platform
+                .to_string()),
+        jar_info.version.as_deref().unwrap_or("unknown"),
+    );
+
+    for (own, other) in ports::get_conflicts(&name, &ports::scan()?) {
+        eprintln!(
+            "Warning: {} {} is also used by {}",
+            own.key, own.port, other.server
+        );
+    }
+
+    Ok(name)
+}
+
+// archives are expected to hold a single server directory, as produced by `export`
+fn import_archive(path: &Path, name: Option<String>) -> Result<String> {
+    let staging_dir = get_expanded_servers_dir()?.join(format!(".import-{}", process::id()));
+    fs::create_dir_all(&staging_dir)?;
+
+    let result = (|| {
+        archive::unpack(File::open(path)?, &staging_dir)?;
+
+        let mut entries = fs::read_dir(&staging_dir)?.collect::<io::Result<Vec<_>>>()?;
+        let server_dir = match (entries.pop(), entries.is_empty()) {
+            (Some(entry), true) if entry.file_type()?.is_dir() => entry.path(),
+            _ => {
+                return Err(Error::InvalidArchive {
+                    file: path.to_path_buf(),
+                });
+            }
+        };
+
+        import_directory(&server_dir, name, false)
+    })();
+
+    server::remove_dir_with_retries(&staging_dir)?;
+
+    result
+}
+
+pub fn import(path: impl AsRef<Path>, name: Option<String>, link: bool) -> Result<String> {
+    let path = fs::canonicalize(path.as_ref())?;
+
+    if path.is_file() && is_archive(&path) {
+        if link {
+            eprintln!("Warning: archives are always extracted, --link is ignored");
+        }
+
+        return import_archive(&path, name);
+    }
+
+    if !path.is_dir() {
+        return Err(Error::MissingDirectory { dir: path });
+    }
+
+    import_directory(&path, name, link)
+}
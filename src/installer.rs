@@ -0,0 +1,166 @@
+use crate::{
+    cache::CachedJar,
+    config,
+    error::{Error, Result},
+    launch::Launch,
+    platforms::{Download, Platform},
+    server::{self, METADATA_DIRECTORY},
+};
+use std::{
+    env,
+    fs::{self, File},
+    path::Path,
+    process::{self, Command, Stdio},
+};
+
+const LOG_FILE: &str = "installer.log";
+const QUILT_LAUNCH_JAR: &str = "quilt-server-launch.jar";
+
+pub struct Installed {
+    pub jar_file_name: Option<String>,
+    pub launch: Launch,
+}
+
+// modern forge installs are started through an args file, everything else through a runnable jar
+fn detect(platform: &Platform, download: &Download, server_dir: &Path) -> Result<Installed> {
+    let build = download.build.as_deref().unwrap_or_default();
+
+    let (args_file, jars) = match platform {
+        Platform::Forge => {
+            let artifact = format!("{}-{build}", download.version);
+            let prefix = format!("{platform}-{artifact}");
+            (
+                Some(format!(
+                    "libraries/net/minecraftforge/forge/{artifact}/unix_args.txt"
+                )),
+                vec![
+                    format!("{prefix}.jar"),
+                    format!("{prefix}-universal.jar"),
+                    format!("{prefix}-shim.jar"),
+                ],
+            )
+        }
+        Platform::Neoforge => (
+            Some(format!(
+                "libraries/net/neoforged/neoforge/{build}/unix_args.txt"
+            )),
+            vec![],
+        ),
+        Platform::Quilt => (None, vec![QUILT_LAUNCH_JAR.to_string()]),
+        Platform::Spigot => (None, vec![format!("spigot-{}.jar", download.version)]),
+        _ => (None, vec![]),
+    };
+
+    let jar_file_name = jars.into_iter().find(|jar| server_dir.join(jar).is_file());
+
+    // the args file only belongs in the launch file, jar_file.txt names a jar or nothing at all
+    if let Some(args_file) = args_file
+        && server_dir.join(&args_file).is_file()
+    {
+        return Ok(Installed {
+            jar_file_name,
+            launch: Launch::ArgsFile { path: args_file },
+        });
+    }
+
+    match jar_file_name {
+        Some(jar_file_name) => Ok(Installed {
+            jar_file_name: Some(jar_file_name),
+            launch: Launch::Jar,
+        }),
+        None => Err(Error::NoServerJar {
+            dir: server_dir.to_path_buf(),
+        }),
+    }
+}
+
+fn get_installer_args(platform: &Platform, download: &Download, server_dir: &Path) -> Vec<String> {
+    match platform {
+        Platform::Quilt => vec![
+            "install".to_string(),
+            "server".to_string(),
+            download.version.clone(),
+            download.build.clone().unwrap_or_default(),
+            "--download-server".to_string(),
+            "--install-dir=.".to_string(),
+        ],
+        Platform::Spigot => vec![
+            "--rev".to_string(),
+            download.version.clone(),
+            "--compile".to_string(),
+            "spigot".to_string(),
+            "--output-dir".to_string(),
+            server_dir.to_string_lossy().to_string(),
+        ],
+        _ => vec!["--installServer".to_string()],
+    }
+}
+
+// installers run inside the server directory, which also updates an existing install, while
+// buildtools clones and compiles spigot in a scratch directory and only outputs the jar
+pub fn install(
+    platform: &Platform,
+    download: &Download,
+    installer: &CachedJar,
+    server_dir: impl AsRef<Path>,
+) -> Result<Installed> {
+    let server_dir = fs::canonicalize(server_dir)?;
+    let metadata_dir = server_dir.join(METADATA_DIRECTORY);
+    fs::create_dir_all(&metadata_dir)?;
+
+    let work_dir = match platform {
+        Platform::Spigot => env::temp_dir().join(format!("mcserver-buildtools-{}", process::id())),
+        _ => server_dir.clone(),
+    };
+    fs::create_dir_all(&work_dir)?;
+
+    let installer_path = work_dir.join(&installer.file_name);
+    fs::copy(&installer.path, &installer_path)?;
+
+    if let Some(java_major) = download.java_major
+        && !config::has_java(java_major)?
+    {
+        eprintln!(
+            "Warning: {platform} {} needs Java {java_major}, but no path is configured for it in java_paths",
+            download.version
+        );
+    }
+
+    match platform {
+        Platform::Spigot => println!("Running BuildTools, this can take several minutes..."),
+        _ => println!("Running the {platform} installer..."),
+    }
+
+    let log_path = metadata_dir.join(LOG_FILE);
+    let log = File::create(&log_path)?;
+    let java = config::get_java(download.java_major)?;
+    let java_args = config::get()?.default_java_args.clone();
+
+    let status = Command::new(java)
+        .args(java_args.split_whitespace())
+        .arg("-jar")
+        .arg(&installer.file_name)
+        .args(get_installer_args(platform, download, &server_dir))
+        .current_dir(&work_dir)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log)
+        .status();
+
+    // installers leave their own log next to the jar
+    if work_dir == server_dir {
+        fs::remove_file(&installer_path)?;
+        let installer_log = server_dir.join(format!("{}.log", installer.file_name));
+        if installer_log.is_file() {
+            fs::remove_file(installer_log)?;
+        }
+    } else {
+        server::remove_dir_with_retries(&work_dir)?;
+    }
+
+    if !status?.success() {
+        return Err(Error::InstallerFailed { log: log_path });
+    }
+
+    detect(platform, download, &server_dir)
+}
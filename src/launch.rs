@@ -0,0 +1,181 @@
+use crate::{
+    config,
+    error::{Error, Result},
+    platforms,
+    server::{self, METADATA_DIRECTORY},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display, Formatter},
+    fs,
+    path::Path,
+};
+
+pub const LAUNCH_FILE: &str = "launch.toml";
+const USER_JVM_ARGS_FILE: &str = "user_jvm_args.txt";
+const DEFAULT_MEMORY: &str = "2G";
+
+// how the server is started, servers without a launch file run their jar with `java -jar`
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "strategy", rename_all = "kebab-case")]
+pub enum Launch {
+    #[default]
+    Jar,
+    // modern forge and neoforge install a java args file instead of a runnable jar
+    ArgsFile {
+        path: String,
+    },
+    // a wrapper script that starts java itself
+    Script {
+        path: String,
+    },
+    // a shell command with {java}, {jar} and {memory} placeholders
+    Command {
+        template: String,
+        memory: Option<String>,
+    },
+}
+
+impl Display for Launch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Jar => write!(f, "jar"),
+            Self::ArgsFile { path } => write!(f, "args file ({path})"),
+            Self::Script { path } => write!(f, "script ({path})"),
+            Self::Command { template, .. } => write!(f, "command ({template})"),
+        }
+    }
+}
+
+// arguments are only quoted when the shell would otherwise interpret them
+pub fn quote(arg: impl AsRef<str>) -> String {
+    let arg = arg.as_ref();
+
+    if !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./@=:+,".contains(c))
+    {
+        return arg.to_string();
+    }
+
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+impl Launch {
+    // a shell command line that starts the server from its directory
+    pub fn command_line(&self, server_dir: impl AsRef<Path>, nogui: bool) -> Result<String> {
+        let server_dir = server_dir.as_ref();
+        let java_args = config::get()?.default_java_args.clone();
+        let jar_info = server::get_jar_info(server_dir)?.unwrap_or_default();
+        let java_major = jar_info.java_major;
+        // proxies don't accept nogui
+        let nogui = nogui
+            && !jar_info
+                .platform
+                .is_some_and(|platform| platforms::is_proxy(&platform));
+        let java = quote(config::get_java(java_major)?);
+
+        let mut args = match self {
+            Self::Jar => vec![
+                java,
+                java_args,
+                "-jar".to_string(),
+                quote(server::get_dir_name(server::get_server_jar_path(
+                    server_dir,
+                )?)),
+            ],
+            Self::ArgsFile { path } => {
+                let mut args = vec![java, java_args];
+                if server_dir.join(USER_JVM_ARGS_FILE).is_file() {
+                    args.push(quote(format!("@{USER_JVM_ARGS_FILE}")));
+                }
+                args.push(quote(format!("@{path}")));
+                args
+            }
+            Self::Script { path } => {
+                let script = server_dir.join(path);
+                if !script.is_file() {
+                    return Err(Error::MissingFile { file: script });
+                }
+
+                if Path::new(path).is_absolute() {
+                    vec![quote(path)]
+                } else {
+                    vec![quote(format!("./{path}"))]
+                }
+            }
+            // templates are complete command lines, so nogui isn't appended
+            Self::Command { template, memory } => {
+                let jar = server::get_dir_name(server::get_server_jar_path(server_dir)?);
+
+                return Ok(template
+                    .replace("{java}", &java)
+                    .replace("{jar}", &quote(jar))
+                    .replace("{memory}", memory.as_deref().unwrap_or(DEFAULT_MEMORY)));
+            }
+        };
+
+        if nogui {
+            args.push("nogui".to_string());
+        }
+
+        Ok(args
+            .into_iter()
+            .filter(|arg| !arg.is_empty())
+            .collect::<Vec<_>>()
+            .join(" "))
+    }
+}
+
+pub fn read(server_dir: impl AsRef<Path>) -> Result<Launch> {
+    let path = server_dir
+        .as_ref()
+        .join(METADATA_DIRECTORY)
+        .join(LAUNCH_FILE);
+
+    if !path.is_file() {
+        return Ok(Launch::default());
+    }
+
+    Ok(toml::from_str(&fs::read_to_string(path)?)?)
+}
+
+pub fn write(server_dir: impl AsRef<Path>, launch: &Launch) -> Result<()> {
+    let path = server_dir
+        .as_ref()
+        .join(METADATA_DIRECTORY)
+        .join(LAUNCH_FILE);
+
+    if *launch == Launch::Jar {
+        if path.is_file() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+
+    fs::write(path, toml::to_string(launch)?)?;
+    Ok(())
+}
+
+// shows the launch strategy of a server, or replaces it when one is given
+pub fn run(server: impl AsRef<str>, launch: Option<Launch>) -> Result<()> {
+    let server = server.as_ref();
+    let server_dir = server::get_server_dir_required(server)?;
+
+    let Some(launch) = launch else {
+        let launch = read(&server_dir)?;
+        let nogui = config::get()?.nogui;
+
+        println!("{server} is launched with {launch}");
+        println!("{}", launch.command_line(&server_dir, nogui)?);
+        return Ok(());
+    };
+
+    // catch a missing script or jar before the next start fails
+    launch.command_line(&server_dir, false)?;
+    write(&server_dir, &launch)?;
+    println!("{server} is now launched with {launch}");
+
+    Ok(())
+}
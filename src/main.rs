@@ -1,11 +1,34 @@
+mod archive;
+mod backup;
+mod cache;
+mod checksum;
 mod cli;
 mod config;
 mod config_defs;
+mod drift;
+mod encryption;
 mod error;
+mod exclude;
+mod export;
+mod import;
+mod installer;
+mod launch;
+mod network;
+mod outdated;
+mod overlay;
 mod platforms;
+mod ports;
+mod progress;
+mod properties;
+mod providers;
 mod rcon;
+mod revision;
+mod s3;
 mod server;
 mod session;
+mod source;
+mod template;
+mod yaml;
 
 use clap::Parser;
 use cli::*;
@@ -19,6 +42,35 @@ fn main() -> Result<()> {
     match args.command {
         Commands::Attach { server } => session::attach(unwrap_server_or_default!(server)?)
             .wrap_err("Failed to attach to session session")?,
+        Commands::Backup { action } => match action {
+            BackupCommands::Create { server } => {
+                backup::create(unwrap_server_or_default!(server)?)
+                    .wrap_err("Failed to create backup")?;
+            }
+            BackupCommands::List { server, target } => {
+                for backup in backup::list(unwrap_server_or_default!(server)?, target)
+                    .wrap_err("Failed to list backups")?
+                {
+                    println!("{backup}");
+                }
+            }
+            BackupCommands::Restore {
+                server,
+                backup,
+                target,
+            } => backup::restore(unwrap_server_or_default!(server)?, backup, target)
+                .wrap_err("Failed to restore backup")?,
+        },
+        Commands::Cache { action } => match action {
+            CacheCommands::List => cache::list().wrap_err("Failed to list cached jars")?,
+            CacheCommands::Clean { unused } => {
+                cache::clean(unused).wrap_err("Failed to clean the cache")?
+            }
+        },
+        Commands::Clone { src, dst, excludes } => {
+            server::clone(config::server_or_current(src)?, &dst, &excludes)
+                .wrap_err_with(|| format!("Failed to clone server to {dst}"))?
+        }
         Commands::Config { config_type } => match config_type {
             ConfigType::Static => println!("{:#?}", config::get_static()),
             ConfigType::Dynamic => println!("{:#?}", config::get()?),
@@ -39,14 +91,52 @@ fn main() -> Result<()> {
         }
         Commands::Deploy { server } => {
             let server = unwrap_server_or_default!(server)?;
+            ports::check_deployable(&server).wrap_err("Failed to check server ports")?;
             session::new_server(&server, Some(server::get_command(&server)?))?;
         }
+        Commands::Drift { server, apply } => drift::run(unwrap_server_or_default!(server)?, apply)
+            .wrap_err("Failed to check server for drift")?,
         Commands::Execute { server, commands } => {
             let session_name = session::get_name(unwrap_server_or_default!(server)?);
             for command in commands {
                 session::write_line(&session_name, command)?;
             }
         }
+        Commands::Export {
+            server,
+            output,
+            without_world,
+            without_logs,
+            excludes,
+        } => export::export(
+            unwrap_server_or_default!(server)?,
+            output,
+            without_world,
+            without_logs,
+            excludes,
+        )
+        .wrap_err("Failed to export server")?,
+        Commands::Import { path, name, link } => {
+            import::import(&path, name, link)
+                .wrap_err_with(|| format!("Failed to import {}", path.display()))?;
+        }
+        Commands::Launch {
+            server,
+            jar,
+            args_file,
+            script,
+            command,
+            memory,
+        } => {
+            let launch = args_file
+                .map(|path| launch::Launch::ArgsFile { path })
+                .or(script.map(|path| launch::Launch::Script { path }))
+                .or(command.map(|template| launch::Launch::Command { template, memory }))
+                .or(jar.then_some(launch::Launch::Jar));
+
+            launch::run(unwrap_server_or_default!(server)?, launch)
+                .wrap_err("Failed to configure the launch strategy")?
+        }
         Commands::List {
             active,
             inactive,
@@ -73,39 +163,125 @@ fn main() -> Result<()> {
                 session::tag_servers(&mut servers).wrap_err("Failed to tag active servers")?;
             }
 
+            ports::tag_conflicting_servers(&mut servers)
+                .wrap_err("Failed to check for port conflicts")?;
+
             for server in servers {
                 println!("{server}");
             }
         }
+        Commands::Outdated { update, backup } => {
+            outdated::run(update, backup).wrap_err("Failed to check for outdated servers")?
+        }
+        Commands::Platforms => platforms::list().wrap_err("Failed to list platforms")?,
+        Commands::Props {
+            server,
+            game_version,
+            action,
+        } => {
+            let server = unwrap_server_or_default!(server)?;
+            let game_version = match game_version {
+                Some(game_version) => Some(game_version),
+                None => server::get_jar_info(server::get_server_dir_required(&server)?)
+                    .wrap_err("Failed to read server metadata")?
+                    .and_then(|jar_info| jar_info.version),
+            };
+            let game_version = game_version.as_deref();
+
+            match action {
+                PropsCommands::Get { key } => println!(
+                    "{}",
+                    properties::get_value(&server, key).wrap_err("Failed to get property")?
+                ),
+                PropsCommands::Set { key, value, force } => {
+                    properties::set_value(&server, key, value, game_version, force)
+                        .wrap_err("Failed to set property")?
+                }
+                PropsCommands::List => {
+                    properties::list(&server, game_version).wrap_err("Failed to list properties")?
+                }
+                PropsCommands::Diff { other } => properties::diff(&server, other, game_version)
+                    .wrap_err("Failed to compare properties")?,
+            }
+        }
         Commands::Rcon { server, commands } => {
             rcon::run(unwrap_server_or_default!(server)?, commands)
                 .wrap_err("Failed to run rcon command")?
         }
+        Commands::Network { action } => {
+            match action {
+                NetworkCommands::Create {
+                    name,
+                    proxy,
+                    backends,
+                } => network::create(&name, proxy, backends)
+                    .wrap_err_with(|| format!("Failed to create network {name}"))?,
+                NetworkCommands::List => network::list().wrap_err("Failed to list networks")?,
+                NetworkCommands::Delete { name } => network::delete(&name)
+                    .wrap_err_with(|| format!("Failed to delete network {name}"))?,
+                NetworkCommands::Apply { name } => network::apply(&name)
+                    .wrap_err_with(|| format!("Failed to configure network {name}"))?,
+                NetworkCommands::Start { name } => network::start(&name)
+                    .wrap_err_with(|| format!("Failed to start network {name}"))?,
+                NetworkCommands::Stop { name } => network::stop(&name)
+                    .wrap_err_with(|| format!("Failed to stop network {name}"))?,
+            }
+        }
         Commands::New {
             platform,
             version,
             name,
-        } => server::create_new(platform, version, name)
-            .wrap_err(format!("Failed to create {platform} server"))?,
+            accept_eula,
+            init,
+        } => {
+            let message = format!("Failed to create {platform} server");
+            server::create_new(platform, version, name, accept_eula, init).wrap_err(message)?
+        }
         Commands::Remove { servers, force } => if force {
             server::remove_servers(servers)
         } else {
             server::remove_servers_with_confirmation(servers)
         }
         .wrap_err("Failed to remove server")?,
+        Commands::Rename { old, new } => server::rename(config::server_or_current(old)?, &new)
+            .wrap_err_with(|| format!("Failed to rename server to {new}"))?,
         Commands::Restart => server::restart().wrap_err("Failed to restart server")?,
+        Commands::Rollback { server } => revision::rollback(unwrap_server_or_default!(server)?)
+            .wrap_err("Failed to roll back server")?,
         Commands::Stop { server } => {
             let server = unwrap_server_or_default!(server)?;
             rcon::run(&server, vec!["stop"])
                 .wrap_err_with(|| format!("Failed to stop server {}", &server))?;
         }
         Commands::Template { action } => match action {
-            TemplateCommands::New { server } => server::new_template(&server)
+            TemplateCommands::New { server, excludes } => template::new(&server, &excludes)
                 .wrap_err_with(|| format!("Failed to create template with server {server}"))?,
-            TemplateCommands::From { template, server } => {
-                server::from_template(&template, server.as_deref())
-                    .wrap_err_with(|| format!("Failed to use template {template}"))?
+            TemplateCommands::From {
+                template,
+                overlays,
+                server,
+                assignments,
+            } => template::create_server(
+                &template,
+                &overlays,
+                server.as_deref(),
+                assignments.into_iter().collect(),
+            )
+            .wrap_err_with(|| format!("Failed to use template {template}"))?,
+            TemplateCommands::List => template::list().wrap_err("Failed to list templates")?,
+            TemplateCommands::Show { template } => template::show(&template)
+                .wrap_err_with(|| format!("Failed to show template {template}"))?,
+            TemplateCommands::Delete { templates, force } => {
+                template::delete(templates, force).wrap_err("Failed to delete template")?
             }
+            TemplateCommands::Rename { old, new } => template::rename(&old, &new)
+                .wrap_err_with(|| format!("Failed to rename template to {new}"))?,
+            TemplateCommands::UpdateFrom {
+                template,
+                server,
+                excludes,
+            } => template::update_from(&template, &server, &excludes)
+                .wrap_err_with(|| format!("Failed to update template {template}"))?,
         },
         Commands::Reinstall {
             git,
@@ -129,8 +305,11 @@ fn main() -> Result<()> {
             server,
             platform,
             version,
-        } => server::update_existing(server, platform, version)
+            backup,
+        } => server::update_existing(server, platform, version, backup)
             .wrap_err("Failed to update server")?,
+        Commands::Versions { platform, version } => platforms::list_versions(&platform, version)
+            .wrap_err_with(|| format!("Failed to list {platform} versions"))?,
     };
 
     config::CONFIG.write()?;
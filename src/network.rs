@@ -0,0 +1,531 @@
+use crate::{
+    config,
+    config_defs::NetworkConfig,
+    error::{Error, Result},
+    overlay,
+    platforms::{self, Platform},
+    ports,
+    properties::{self, Properties},
+    server, session, yaml,
+};
+use rand::{Rng, distr::Alphanumeric};
+use serde_norway::{Mapping, Value};
+use std::{
+    collections::BTreeMap,
+    fs,
+    net::TcpStream,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+use toml_edit::{Array, DocumentMut, Item};
+
+const VELOCITY_CONFIG: &str = "velocity.toml";
+const FORWARDING_SECRET_FILE: &str = "forwarding.secret";
+const BUNGEECORD_CONFIG: &str = "config.yml";
+const PAPER_GLOBAL_CONFIG: &str = "config/paper-global.yml";
+const LEGACY_PAPER_CONFIG: &str = "paper.yml";
+const SPIGOT_CONFIG: &str = "spigot.yml";
+
+const SECRET_LENGTH: usize = 32;
+const DEFAULT_SERVER_PORT: u16 = 25565;
+
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(180);
+const STARTUP_POLL_INTERVAL: Duration = Duration::from_secs(2);
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(60);
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+struct Backend {
+    name: String,
+    dir: PathBuf,
+    port: u16,
+    platform: Option<Platform>,
+}
+
+// velocity forwards players with a shared secret, bungeecord only passes their ip along
+enum Forwarding {
+    Modern { secret: String },
+    Legacy,
+}
+
+fn get_network(name: &str) -> Result<NetworkConfig> {
+    config::get()?
+        .networks
+        .get(name)
+        .cloned()
+        .ok_or_else(|| Error::NetworkNotFound(name.to_string()))
+}
+
+fn get_platform(server_dir: &Path) -> Result<Option<Platform>> {
+    Ok(server::get_jar_info(server_dir)?.and_then(|jar_info| jar_info.platform))
+}
+
+fn get_port(server_dir: &Path) -> Result<u16> {
+    let path = server_dir.join(properties::FILE_NAME);
+
+    if !path.is_file() {
+        return Ok(DEFAULT_SERVER_PORT);
+    }
+
+    Ok(Properties::read(path)?
+        .get("server-port")
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(DEFAULT_SERVER_PORT))
+}
+
+fn get_backends(network: &NetworkConfig) -> Result<Vec<Backend>> {
+    network
+        .backends
+        .iter()
+        .map(|name| {
+            let dir = server::get_server_dir_required(name)?;
+            Ok(Backend {
+                name: name.clone(),
+                port: get_port(&dir)?,
+                platform: get_platform(&dir)?,
+                dir,
+            })
+        })
+        .collect()
+}
+
+pub fn create(name: &str, proxy: String, backends: Vec<String>) -> Result<()> {
+    if config::get()?.networks.contains_key(name) {
+        return Err(Error::NetworkAlreadyExists(name.to_string()));
+    }
+
+    let proxy_dir = server::get_server_dir_required(&proxy)?;
+    if !get_platform(&proxy_dir)?.is_some_and(|platform| platforms::is_proxy(&platform)) {
+        return Err(Error::NotProxy(proxy));
+    }
+
+    for backend in &backends {
+        server::get_server_dir_required(backend)?;
+    }
+
+    config::get()?
+        .networks
+        .insert(name.to_string(), NetworkConfig { proxy, backends });
+
+    println!("Created {name}, run `mcserver network apply {name}` to configure forwarding");
+
+    Ok(())
+}
+
+pub fn list() -> Result<()> {
+    let networks: BTreeMap<_, _> = config::get()?.networks.clone().into_iter().collect();
+
+    for (name, network) in networks {
+        println!(
+            "{name}: {} -> {}",
+            network.proxy,
+            network.backends.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+// only the declaration is removed, the servers keep their forwarding configuration
+pub fn delete(name: &str) -> Result<()> {
+    if config::get()?.networks.remove(name).is_none() {
+        return Err(Error::NetworkNotFound(name.to_string()));
+    }
+
+    println!("Deleted {name}");
+
+    Ok(())
+}
+
+fn generate_secret() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(SECRET_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+// an existing secret is kept, so that backends outside of the network keep working
+fn get_secret(proxy_dir: &Path) -> Result<String> {
+    let path = proxy_dir.join(FORWARDING_SECRET_FILE);
+
+    if path.is_file() {
+        let secret = fs::read_to_string(&path)?.trim().to_string();
+        if !secret.is_empty() {
+            return Ok(secret);
+        }
+    }
+
+    let secret = generate_secret();
+    fs::write(path, &secret)?;
+    Ok(secret)
+}
+
+// velocity.toml is edited in place so that its comments are kept
+fn configure_velocity(proxy_dir: &Path, backends: &[Backend]) -> Result<String> {
+    let path = proxy_dir.join(VELOCITY_CONFIG);
+    if !path.is_file() {
+        return Err(Error::MissingFile { file: path });
+    }
+
+    let mut document = fs::read_to_string(&path)?.parse::<DocumentMut>()?;
+    let names: Vec<&str> = backends
+        .iter()
+        .map(|backend| backend.name.as_str())
+        .collect();
+
+    document["player-info-forwarding-mode"] = toml_edit::value("modern");
+    document["forwarding-secret-file"] = toml_edit::value(FORWARDING_SECRET_FILE);
+
+    let servers = &mut document["servers"];
+    if !servers.is_table() {
+        *servers = toml_edit::table();
+    }
+    if let Some(servers) = servers.as_table_mut() {
+        // the example servers from the default config would be tried otherwise
+        servers.retain(|key, _| names.contains(&key));
+
+        for backend in backends {
+            servers[&backend.name] = toml_edit::value(format!("127.0.0.1:{}", backend.port));
+        }
+        servers["try"] = toml_edit::value(Array::from_iter(names.iter().copied()));
+    }
+
+    // velocity refuses to start when a forced host points at an unknown server
+    if let Some(forced_hosts) = document
+        .get_mut("forced-hosts")
+        .and_then(Item::as_table_mut)
+    {
+        for (_, servers) in forced_hosts.iter_mut() {
+            if let Some(servers) = servers.as_array_mut() {
+                servers.retain(|server| {
+                    server
+                        .as_str()
+                        .is_some_and(|server| names.contains(&server))
+                });
+            }
+        }
+        forced_hosts
+            .retain(|_, servers| servers.as_array().is_none_or(|servers| !servers.is_empty()));
+    }
+
+    fs::write(path, document.to_string())?;
+
+    get_secret(proxy_dir)
+}
+
+fn configure_bungeecord(proxy_dir: &Path, backends: &[Backend]) -> Result<()> {
+    let path = proxy_dir.join(BUNGEECORD_CONFIG);
+    if !path.is_file() {
+        return Err(Error::MissingFile { file: path });
+    }
+
+    let contents = fs::read_to_string(&path)?;
+    let mut config: Value = serde_norway::from_str(&contents)?;
+
+    let mut servers = Mapping::new();
+    for backend in backends {
+        let mut server = Mapping::new();
+        server.insert("motd".into(), backend.name.clone().into());
+        server.insert(
+            "address".into(),
+            format!("127.0.0.1:{}", backend.port).into(),
+        );
+        server.insert("restricted".into(), false.into());
+        servers.insert(backend.name.clone().into(), server.into());
+    }
+
+    let priorities: Vec<Value> = backends
+        .iter()
+        .map(|backend| backend.name.clone().into())
+        .collect();
+
+    let mut edits = vec![
+        ("servers", Value::from(servers)),
+        ("ip_forward", true.into()),
+    ];
+
+    // the listeners are a sequence, so they're rewritten as a whole with their priorities changed
+    if let Some(Value::Sequence(listeners)) = config.get("listeners") {
+        let mut listeners = listeners.clone();
+        for listener in listeners.iter_mut().filter_map(Value::as_mapping_mut) {
+            listener.insert("priorities".into(), priorities.clone().into());
+        }
+        edits.push(("listeners", listeners.into()));
+    }
+
+    let edited = edits.iter().try_fold(contents, |contents, (key, value)| {
+        yaml::set(&contents, &[key], value)
+    });
+
+    let contents = match edited {
+        Some(contents) => contents,
+        None => {
+            if let Value::Mapping(config) = &mut config {
+                for (key, value) in edits {
+                    config.insert(key.into(), value);
+                }
+            }
+
+            eprintln!(
+                "Warning: {} couldn't be edited in place, so its comments are lost",
+                path.display()
+            );
+            serde_norway::to_string(&config)?
+        }
+    };
+    fs::write(path, contents)?;
+
+    Ok(())
+}
+
+// sets a nested value in a yaml config, creating the file if the server hasn't generated it yet.
+// the file is edited in place so that paper's comments are kept, and only re-serialized when it's
+// laid out in a way that can't be edited
+fn set_yaml(path: &Path, keys: &[&str], value: Value) -> Result<()> {
+    let contents = if path.is_file() {
+        fs::read_to_string(path)?
+    } else {
+        String::new()
+    };
+
+    let value = keys.iter().rev().fold(value, |value, key| {
+        let mut mapping = Mapping::new();
+        mapping.insert((*key).into(), value);
+        Value::Mapping(mapping)
+    });
+
+    let contents = match yaml::merge(&contents, &value) {
+        Some(contents) => contents,
+        None => {
+            let mut config: Value = serde_norway::from_str(&contents)?;
+            if config.is_null() {
+                config = Value::Mapping(Mapping::new());
+            }
+            overlay::merge_yaml(&mut config, value);
+
+            eprintln!(
+                "Warning: {} couldn't be edited in place, so its comments are lost",
+                path.display()
+            );
+            serde_norway::to_string(&config)?
+        }
+    };
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents)?;
+
+    Ok(())
+}
+
+fn supports(platform: Option<&Platform>, forwarding: &Forwarding) -> bool {
+    match forwarding {
+        Forwarding::Modern { .. } => matches!(
+            platform,
+            Some(Platform::Folia | Platform::Paper | Platform::Purpur)
+        ),
+        Forwarding::Legacy => matches!(
+            platform,
+            Some(Platform::Folia | Platform::Paper | Platform::Purpur | Platform::Spigot)
+        ),
+    }
+}
+
+fn configure_backend(backend: &Backend, forwarding: &Forwarding) -> Result<()> {
+    if !supports(backend.platform.as_ref(), forwarding) {
+        eprintln!(
+            "Warning: {} runs {}, which needs a proxy mod or plugin to accept forwarded players",
+            backend.name,
+            backend.platform.as_ref().map_or_else(
+                || "an unknown platform".to_string(),
+                |platform| platform.to_string()
+            )
+        );
+    }
+
+    // the proxy authenticates players, so backends must not
+    let properties_path = backend.dir.join(properties::FILE_NAME);
+    let mut server_properties = if properties_path.is_file() {
+        Properties::read(&properties_path)?
+    } else {
+        Properties::default()
+    };
+    server_properties.set("online-mode", "false");
+    server_properties.write(&properties_path)?;
+
+    let paper_global = backend.dir.join(PAPER_GLOBAL_CONFIG);
+    let legacy_paper = backend.dir.join(LEGACY_PAPER_CONFIG);
+    let spigot = backend.dir.join(SPIGOT_CONFIG);
+
+    match forwarding {
+        Forwarding::Modern { secret } => {
+            let mut velocity = Mapping::new();
+            velocity.insert("enabled".into(), true.into());
+            velocity.insert("online-mode".into(), true.into());
+            velocity.insert("secret".into(), secret.clone().into());
+
+            // servers from before 1.19 keep their paper settings in a single file
+            if legacy_paper.is_file() && !paper_global.is_file() {
+                set_yaml(
+                    &legacy_paper,
+                    &["settings", "velocity-support"],
+                    velocity.into(),
+                )?;
+            } else {
+                set_yaml(&paper_global, &["proxies", "velocity"], velocity.into())?;
+            }
+
+            if spigot.is_file() {
+                set_yaml(&spigot, &["settings", "bungeecord"], false.into())?;
+            }
+        }
+        Forwarding::Legacy => {
+            set_yaml(&spigot, &["settings", "bungeecord"], true.into())?;
+
+            if paper_global.is_file() {
+                set_yaml(
+                    &paper_global,
+                    &["proxies", "velocity", "enabled"],
+                    false.into(),
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn apply(name: &str) -> Result<()> {
+    let network = get_network(name)?;
+    let proxy_dir = server::get_server_dir_required(&network.proxy)?;
+    let backends = get_backends(&network)?;
+
+    let forwarding = match get_platform(&proxy_dir)? {
+        Some(Platform::Velocity) => Forwarding::Modern {
+            secret: configure_velocity(&proxy_dir, &backends)?,
+        },
+        Some(Platform::Bungeecord | Platform::Waterfall) => {
+            configure_bungeecord(&proxy_dir, &backends)?;
+            Forwarding::Legacy
+        }
+        _ => return Err(Error::NotProxy(network.proxy)),
+    };
+    println!("Configured {}", network.proxy);
+
+    for backend in &backends {
+        configure_backend(backend, &forwarding)?;
+        println!("Configured {}", backend.name);
+    }
+
+    for server in [&network.proxy].into_iter().chain(&network.backends) {
+        if session::is_active(server)? {
+            eprintln!(
+                "Warning: {server} is running and must be restarted for the changes to apply"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn deploy(server: &str) -> Result<()> {
+    if session::is_active(server)? {
+        println!("{server} is already running");
+        return Ok(());
+    }
+
+    ports::check_deployable(server)?;
+    session::new_detached_server(server, Some(server::get_command(server)?))?;
+    println!("Started {server}");
+
+    Ok(())
+}
+
+fn wait_until_listening(backend: &Backend) -> bool {
+    let start = Instant::now();
+
+    while start.elapsed() < STARTUP_TIMEOUT {
+        if TcpStream::connect(("127.0.0.1", backend.port)).is_ok() {
+            return true;
+        }
+        thread::sleep(STARTUP_POLL_INTERVAL);
+    }
+
+    false
+}
+
+// the proxy is started last, so that players aren't sent to backends that are still starting
+pub fn start(name: &str) -> Result<()> {
+    let network = get_network(name)?;
+    let backends = get_backends(&network)?;
+
+    for backend in &backends {
+        deploy(&backend.name)?;
+    }
+
+    for backend in &backends {
+        println!("Waiting for {} on port {}...", backend.name, backend.port);
+        if !wait_until_listening(backend) {
+            eprintln!(
+                "Warning: {} didn't start listening within {}s",
+                backend.name,
+                STARTUP_TIMEOUT.as_secs()
+            );
+        }
+    }
+
+    deploy(&network.proxy)
+}
+
+fn wait_until_stopped(server: &str) -> Result<bool> {
+    let start = Instant::now();
+
+    while start.elapsed() < SHUTDOWN_TIMEOUT {
+        if !session::is_active(server)? {
+            return Ok(true);
+        }
+        thread::sleep(SHUTDOWN_POLL_INTERVAL);
+    }
+
+    Ok(false)
+}
+
+// the command goes to the server's console, which works whether or not rcon is enabled
+fn shut_down(server: &str, command: &str) -> Result<()> {
+    if !session::is_active(server)? {
+        return Ok(());
+    }
+
+    session::write_line(session::get_name(server), command)?;
+    println!("Stopping {server}...");
+
+    if wait_until_stopped(server)? {
+        println!("Stopped {server}");
+    } else {
+        eprintln!(
+            "Warning: {server} didn't stop within {}s",
+            SHUTDOWN_TIMEOUT.as_secs()
+        );
+    }
+
+    Ok(())
+}
+
+// the proxy is stopped first, so that players aren't left on backends that are shutting down
+pub fn stop(name: &str) -> Result<()> {
+    let network = get_network(name)?;
+
+    let servers = [(&network.proxy, "end")]
+        .into_iter()
+        .chain(network.backends.iter().map(|backend| (backend, "stop")));
+
+    for (server, command) in servers {
+        if let Err(err) = shut_down(server, command) {
+            eprintln!("Failed to stop {server}: {err}");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,207 @@
+use crate::{
+    config::get_expanded_servers_dir,
+    error::{Error, Result},
+    platforms::{self, Platform},
+    server,
+};
+use std::collections::HashMap;
+
+const UNKNOWN: &str = "?";
+
+enum Status {
+    UpToDate,
+    Outdated,
+    Unknown,
+    Failed(String),
+}
+
+struct Row {
+    server: String,
+    platform: Option<Platform>,
+    version: Option<String>,
+    build: Option<String>,
+    latest_build: Option<String>,
+    latest_version: Option<String>,
+    status: Status,
+}
+
+// lookups are shared between servers on the same platform and version
+#[derive(Default)]
+struct Lookups {
+    versions: HashMap<Platform, Option<String>>,
+    builds: HashMap<(Platform, String), Option<String>>,
+}
+
+impl Lookups {
+    fn latest_version(&mut self, platform: &Platform) -> Result<Option<String>> {
+        if let Some(version) = self.versions.get(platform) {
+            return Ok(version.clone());
+        }
+
+        let version = platforms::get_provider(platform)?.latest_version()?;
+        self.versions.insert(platform.clone(), version.clone());
+        Ok(version)
+    }
+
+    fn latest_build(&mut self, platform: &Platform, version: &str) -> Result<Option<String>> {
+        let key = (platform.clone(), version.to_string());
+        if let Some(build) = self.builds.get(&key) {
+            return Ok(build.clone());
+        }
+
+        let build = platforms::get_provider(platform)?.latest_build(version)?;
+        self.builds.insert(key, build.clone());
+        Ok(build)
+    }
+}
+
+fn check(server: String, lookups: &mut Lookups) -> Result<Row> {
+    // unreadable metadata is reported as an unknown status rather than failing every server
+    let jar_info = server::get_jar_info(get_expanded_servers_dir()?.join(&server))
+        .ok()
+        .flatten()
+        .unwrap_or_default();
+
+    let mut row = Row {
+        server,
+        platform: jar_info.platform,
+        version: jar_info.version,
+        build: jar_info.build,
+        latest_build: None,
+        latest_version: None,
+        status: Status::Unknown,
+    };
+
+    let (Some(platform), Some(version)) = (&row.platform, row.version.as_deref()) else {
+        return Ok(row);
+    };
+
+    let lookup = lookups
+        .latest_build(platform, version)
+        .and_then(|build| Ok((build, lookups.latest_version(platform)?)));
+
+    match lookup {
+        Ok((latest_build, latest_version)) => {
+            row.status = match (&row.build, &latest_build) {
+                (Some(build), Some(latest_build)) if build == latest_build => Status::UpToDate,
+                (Some(_), Some(_)) => Status::Outdated,
+                _ => Status::Unknown,
+            };
+            row.latest_build = latest_build;
+            row.latest_version = latest_version;
+        }
+        Err(err) => row.status = Status::Failed(err.to_string()),
+    }
+
+    Ok(row)
+}
+
+fn print_table(rows: &[Row]) {
+    let cells: Vec<[String; 7]> = rows
+        .iter()
+        .map(|row| {
+            let newer_version = match (&row.version, &row.latest_version) {
+                (Some(version), Some(latest_version)) if version != latest_version => {
+                    latest_version.clone()
+                }
+                (_, Some(_)) => "-".to_string(),
+                (_, None) => UNKNOWN.to_string(),
+            };
+
+            [
+                row.server.clone(),
+                row.platform
+                    .as_ref()
+                    .map_or(UNKNOWN.to_string(), |platform| platform.to_string()),
+                row.version.clone().unwrap_or_else(|| UNKNOWN.to_string()),
+                row.build.clone().unwrap_or_else(|| UNKNOWN.to_string()),
+                row.latest_build
+                    .clone()
+                    .unwrap_or_else(|| UNKNOWN.to_string()),
+                newer_version,
+                match &row.status {
+                    Status::UpToDate => "up to date".to_string(),
+                    Status::Outdated => "\x1b[33;1moutdated\x1b[0m".to_string(),
+                    Status::Unknown => "unknown".to_string(),
+                    Status::Failed(err) => format!("\x1b[31;1mfailed\x1b[0m: {err}"),
+                },
+            ]
+        })
+        .collect();
+
+    let header = [
+        "SERVER",
+        "PLATFORM",
+        "VERSION",
+        "BUILD",
+        "LATEST BUILD",
+        "NEWER VERSION",
+        "STATUS",
+    ];
+
+    let mut widths = header.map(str::len);
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let format_row = |row: &[&str]| {
+        row.iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!("{}", format_row(&header));
+    for row in &cells {
+        println!(
+            "{}",
+            format_row(&row.iter().map(String::as_str).collect::<Vec<_>>())
+        );
+    }
+}
+
+pub fn run(update: bool, take_backup: bool) -> Result<()> {
+    let mut servers = vec![];
+    server::for_each(|server| servers.push(server))?;
+    servers.sort();
+
+    let mut lookups = Lookups::default();
+    let rows = servers
+        .into_iter()
+        .map(|server| check(server, &mut lookups))
+        .collect::<Result<Vec<_>>>()?;
+
+    print_table(&rows);
+
+    if !update {
+        return Ok(());
+    }
+
+    let mut failed = vec![];
+    for row in rows
+        .iter()
+        .filter(|row| matches!(row.status, Status::Outdated))
+    {
+        let (Some(platform), Some(version)) = (row.platform.clone(), row.version.clone()) else {
+            continue;
+        };
+
+        println!("Updating {}...", row.server);
+        if let Err(err) = server::update_existing(&row.server, platform, Some(version), take_backup)
+        {
+            eprintln!("Failed to update {}: {err}", row.server);
+            failed.push(row.server.clone());
+        }
+    }
+
+    if !failed.is_empty() {
+        return Err(Error::UpdateFailed(failed.join(", ")));
+    }
+
+    Ok(())
+}
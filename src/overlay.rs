@@ -0,0 +1,180 @@
+use crate::{
+    error::Result,
+    exclude::{self, Excludes},
+    properties::Properties,
+    server::METADATA_DIRECTORY,
+    yaml,
+};
+use std::{fs, path::Path};
+use toml_edit::{DocumentMut, Item, Table, TableLike};
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Properties,
+    Toml,
+    Yaml,
+}
+
+pub fn get_format(path: &Path) -> Option<Format> {
+    match path.extension()?.to_str()?.to_lowercase().as_str() {
+        "properties" => Some(Format::Properties),
+        "toml" => Some(Format::Toml),
+        "yml" | "yaml" => Some(Format::Yaml),
+        _ => None,
+    }
+}
+
+// tables are merged recursively, while any other value in the overlay replaces the base one in
+// place, keeping the base's comments and key order
+fn merge_toml(base: &mut dyn TableLike, overlay: &dyn TableLike) {
+    for (key, item) in overlay.iter() {
+        if let Some(overlay) = item.as_table_like() {
+            if base.get(key).and_then(Item::as_table_like).is_none() {
+                let mut table = Table::new();
+                table.set_implicit(true);
+                base.insert(key, Item::Table(table));
+            }
+            if let Some(base) = base.get_mut(key).and_then(Item::as_table_like_mut) {
+                merge_toml(base, overlay);
+            }
+            continue;
+        }
+
+        match base.get_mut(key) {
+            Some(Item::Value(base)) if item.is_value() => {
+                let decor = base.decor().clone();
+                *base = item.as_value().cloned().unwrap_or_else(|| base.clone());
+                *base.decor_mut() = decor;
+            }
+            _ => {
+                base.insert(key, item.clone());
+            }
+        }
+    }
+}
+
+pub fn merge_yaml(base: &mut serde_norway::Value, overlay: serde_norway::Value) {
+    match (base, overlay) {
+        (serde_norway::Value::Mapping(base), serde_norway::Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(base) => merge_yaml(base, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+// returns None if either file can't be parsed, in which case the overlay replaces the base
+fn merge(format: Format, base: &str, overlay: &str, overlay_path: &Path) -> Option<String> {
+    match format {
+        Format::Properties => {
+            let mut base = Properties::parse(base);
+            for (key, value) in Properties::parse(overlay).iter() {
+                base.set(key, value);
+            }
+            Some(base.to_string())
+        }
+        Format::Toml => {
+            let mut base = base.parse::<DocumentMut>().ok()?;
+            merge_toml(
+                base.as_table_mut(),
+                overlay.parse::<DocumentMut>().ok()?.as_table(),
+            );
+            Some(base.to_string())
+        }
+        Format::Yaml => {
+            let overlay = serde_norway::from_str::<serde_norway::Value>(overlay).ok()?;
+            if let Some(merged) = yaml::merge(base, &overlay) {
+                return Some(merged);
+            }
+
+            // documents that can't be edited in place have to be re-serialized, which drops their
+            // comments and formatting, so that only happens when the overlay changes something
+            let parsed = serde_norway::from_str::<serde_norway::Value>(base).ok()?;
+            let mut merged = parsed.clone();
+            merge_yaml(&mut merged, overlay);
+            if merged == parsed {
+                return Some(base.to_string());
+            }
+
+            eprintln!(
+                "Warning: {} can't be merged in place, so comments in the file it overlays are lost",
+                overlay_path.display()
+            );
+            serde_norway::to_string(&merged).ok()
+        }
+    }
+}
+
+fn apply_file(base_path: &Path, overlay_path: &Path) -> Result<()> {
+    if base_path.is_file()
+        && let Some(format) = get_format(overlay_path)
+    {
+        let merged = merge(
+            format,
+            &fs::read_to_string(base_path)?,
+            &fs::read_to_string(overlay_path)?,
+            overlay_path,
+        );
+
+        match merged {
+            Some(merged) => {
+                fs::write(base_path, merged)?;
+                return Ok(());
+            }
+            None => eprintln!(
+                "Warning: failed to merge {}, replacing it instead",
+                overlay_path.display()
+            ),
+        }
+    }
+
+    fs::copy(overlay_path, base_path)?;
+
+    Ok(())
+}
+
+fn apply_children(
+    base_dir: &Path,
+    overlay_dir: &Path,
+    relative: &Path,
+    excludes: &Excludes,
+) -> Result<()> {
+    fs::create_dir_all(base_dir.join(relative))?;
+
+    for entry in fs::read_dir(overlay_dir.join(relative))? {
+        let entry = entry?;
+        let relative = relative.join(entry.file_name());
+        let is_dir = entry.file_type()?.is_dir();
+
+        // the metadata belongs to the server, and the ignore file only describes the layer
+        if relative == Path::new(METADATA_DIRECTORY)
+            || relative == Path::new(exclude::FILE_NAME)
+            || excludes.is_excluded(&relative, is_dir)
+        {
+            continue;
+        }
+
+        if is_dir {
+            apply_children(base_dir, overlay_dir, &relative, excludes)?;
+        } else {
+            apply_file(&base_dir.join(&relative), &entry.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+// copies an overlay onto a directory, merging structured config files key by key, while skipping
+// whatever the overlay's `.mcserverignore` excludes
+pub fn apply(base_dir: impl AsRef<Path>, overlay_dir: impl AsRef<Path>) -> Result<()> {
+    let overlay_dir = overlay_dir.as_ref();
+    let excludes = Excludes::new(overlay_dir, &[])?;
+
+    apply_children(base_dir.as_ref(), overlay_dir, Path::new(""), &excludes)
+}
@@ -1,16 +1,24 @@
 use crate::{
+    cache::CachedJar,
+    checksum::{self, Algorithm, Checksum},
     config::STATIC_CONFIG,
     error::{Error, Result},
+    installer::{self, Installed},
+    launch::Launch,
+    providers,
 };
-use clap::ValueEnum;
 use reqwest::{
-    self,
+    self, StatusCode,
     blocking::{self, Client},
     header::{HeaderMap, HeaderValue, USER_AGENT},
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 use std::{
+    collections::HashMap,
     fmt::{self, Display, Formatter},
+    io,
+    path::Path,
+    str::FromStr,
     sync::OnceLock,
 };
 use url::Url;
@@ -19,12 +27,44 @@ static CLIENT: OnceLock<Client> = OnceLock::new();
 
 const FABRIC_BASE_API_URL: &str = "https://meta.fabricmc.net/v2/versions";
 
-const PAPER_BASE_API_URL: &str = "https://api.papermc.io/v2/projects/paper";
+const PAPER_BASE_API_URL: &str = "https://api.papermc.io/v2/projects";
 const PAPER_BASE_DOWNLOAD_URL: &str = "https://fill-data.papermc.io/v1/objects";
 
 const PURPUR_BASE_API_URL: &str = "https://api.purpurmc.org/v2/purpur";
 
-fn get_client() -> Result<&'static Client> {
+const FORGE_PROMOTIONS_URL: &str =
+    "https://files.minecraftforge.net/net/minecraftforge/forge/promotions_slim.json";
+const FORGE_MAVEN_URL: &str = "https://maven.minecraftforge.net/net/minecraftforge/forge";
+
+const NEOFORGE_VERSIONS_URL: &str =
+    "https://maven.neoforged.net/api/maven/versions/releases/net/neoforged/neoforge";
+const NEOFORGE_MAVEN_URL: &str = "https://maven.neoforged.net/releases/net/neoforged/neoforge";
+
+const QUILT_META_URL: &str = "https://meta.quiltmc.org/v3/versions";
+const QUILT_INSTALLER_MAVEN_URL: &str =
+    "https://maven.quiltmc.org/repository/release/org/quiltmc/quilt-installer";
+
+const SPIGOT_VERSIONS_URL: &str = "https://hub.spigotmc.org/versions";
+const BUILDTOOLS_URL: &str = "https://hub.spigotmc.org/jenkins/job/BuildTools/lastSuccessfulBuild/artifact/target/BuildTools.jar";
+// how many of the newest game releases are checked for a spigot build
+const SPIGOT_LOOKBACK: usize = 5;
+// java class file versions are offset from java versions by 44
+const CLASS_FILE_OFFSET: u32 = 44;
+
+const BUNGEECORD_JOB_URL: &str = "https://ci.md-5.net/job/BungeeCord";
+
+const VANILLA_MANIFEST_URL: &str =
+    "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json";
+
+pub struct Download {
+    pub url: Url,
+    pub version: String,
+    pub build: Option<String>,
+    pub checksum: Option<Checksum>,
+    pub java_major: Option<u32>,
+}
+
+pub fn get_client() -> Result<&'static Client> {
     if let Some(client) = CLIENT.get() {
         return Ok(client);
     }
@@ -62,27 +102,36 @@ fn first_stable(entries: Vec<FabricEntry>) -> Option<FabricEntry> {
     entries.into_iter().find(|entry| entry.stable)
 }
 
-fn get_fabric(game_version: Option<String>) -> Result<String> {
-    let versions: FabricVersions = blocking::get(FABRIC_BASE_API_URL)?.json()?;
+fn get_fabric_versions() -> Result<FabricVersions> {
+    Ok(blocking::get(FABRIC_BASE_API_URL)?.json()?)
+}
+
+fn get_stable_fabric_version(entries: Vec<FabricEntry>, kind: &str) -> Result<String> {
+    first_stable(entries)
+        .map(|entry| entry.version)
+        .ok_or_else(|| Error::PlatformsNotFound(format!("stable {kind}")))
+}
+
+// the fabric meta api doesn't publish hashes, so the loader version is only recorded as the build
+fn get_fabric(game_version: Option<String>) -> Result<Download> {
+    let versions = get_fabric_versions()?;
 
     let game_version = game_version.map_or_else(
-        || {
-            first_stable(versions.game)
-                .map(|e| e.version)
-                .ok_or_else(|| Error::PlatformsNotFound("stable game version".to_string()))
-        },
+        || get_stable_fabric_version(versions.game, "game version"),
         Ok,
     )?;
-    let loader_version = first_stable(versions.loader)
-        .ok_or_else(|| Error::PlatformsNotFound("stable loader".to_string()))?
-        .version;
-    let installer_version = first_stable(versions.installer)
-        .ok_or_else(|| Error::PlatformsNotFound("stable installer".to_string()))?
-        .version;
+    let loader_version = get_stable_fabric_version(versions.loader, "loader")?;
+    let installer_version = get_stable_fabric_version(versions.installer, "installer")?;
 
-    Ok(format!(
-        "{FABRIC_BASE_API_URL}/loader/{game_version}/{loader_version}/{installer_version}/server/jar",
-    ))
+    Ok(Download {
+        url: Url::parse(&format!(
+            "{FABRIC_BASE_API_URL}/loader/{game_version}/{loader_version}/{installer_version}/server/jar",
+        ))?,
+        version: game_version,
+        build: Some(loader_version),
+        checksum: None,
+        java_major: None,
+    })
 }
 
 #[derive(Debug, Deserialize)]
@@ -97,6 +146,7 @@ struct PaperBuildsInfo {
 
 #[derive(Debug, Deserialize)]
 struct Build {
+    build: u32,
     downloads: PaperDownloads,
 }
 
@@ -111,36 +161,60 @@ struct PaperApplication {
     sha256: String,
 }
 
-fn get_paper(version: Option<String>) -> Result<String> {
-    let client = get_client()?;
-
-    let version = version.map_or_else(
-        || {
-            let project_info: PaperProjectInfo = client.get(PAPER_BASE_API_URL).send()?.json()?;
-            let mut versions = project_info.versions;
-            Ok::<_, Error>(versions.pop().unwrap())
-        },
-        Ok,
-    )?;
+// folia, velocity and waterfall are published through the same api as paper, under their own
+// projects, which list their versions and builds oldest first
+fn get_paper_versions(project: &Platform) -> Result<Vec<String>> {
+    let project_info: PaperProjectInfo = get_client()?
+        .get(format!("{PAPER_BASE_API_URL}/{project}"))
+        .send()?
+        .json()?;
+    Ok(project_info.versions)
+}
 
-    let builds: Vec<Build> = client
-        .get(format!("{PAPER_BASE_API_URL}/versions/{version}/builds"))
+fn get_paper_builds(project: &Platform, version: &str) -> Result<Vec<Build>> {
+    Ok(get_client()?
+        .get(format!(
+            "{PAPER_BASE_API_URL}/{project}/versions/{version}/builds"
+        ))
         .send()?
         .json::<PaperBuildsInfo>()?
-        .builds;
-    let application = &builds[builds.len() - 1].downloads.application;
+        .builds)
+}
 
-    let download_url = format!(
-        "{PAPER_BASE_DOWNLOAD_URL}/{}/{}",
-        application.sha256, application.name
-    );
+fn get_latest_paper_version(project: &Platform) -> Result<String> {
+    get_paper_versions(project)?
+        .pop()
+        .ok_or_else(|| Error::PlatformsNotFound(format!("{project} version")))
+}
+
+fn get_latest_paper_build(project: &Platform, version: &str) -> Result<Build> {
+    get_paper_builds(project, version)?
+        .pop()
+        .ok_or_else(|| Error::PlatformsNotFound(format!("{project} build for {version}")))
+}
+
+fn get_paper(project: &Platform, version: Option<String>) -> Result<Download> {
+    let version = version.map_or_else(|| get_latest_paper_version(project), Ok)?;
 
-    Ok(download_url)
+    let build = get_latest_paper_build(project, &version)?;
+    let application = &build.downloads.application;
+
+    Ok(Download {
+        url: Url::parse(&format!(
+            "{PAPER_BASE_DOWNLOAD_URL}/{}/{}",
+            application.sha256, application.name
+        ))?,
+        version,
+        build: Some(build.build.to_string()),
+        checksum: Some(Checksum::new(Algorithm::Sha256, &application.sha256)),
+        java_major: None,
+    })
 }
 
 #[derive(Debug, Deserialize)]
 struct PurpurProjectInfo {
     metadata: PurpurMetadata,
+    versions: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -156,58 +230,965 @@ struct PurpurVersionInfo {
 #[derive(Debug, Deserialize)]
 struct PurpurBuilds {
     latest: String,
+    all: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PurpurBuildInfo {
+    md5: String,
+}
+
+fn get_purpur_project_info() -> Result<PurpurProjectInfo> {
+    Ok(blocking::get(PURPUR_BASE_API_URL)?.json()?)
+}
+
+fn get_purpur_version_info(version: &str) -> Result<PurpurVersionInfo> {
+    Ok(blocking::get(format!("{PURPUR_BASE_API_URL}/{version}"))?.json()?)
 }
 
 fn get_current_purpur_version() -> Result<String> {
-    let project_info: PurpurProjectInfo = blocking::get(PURPUR_BASE_API_URL)?.json()?;
-    Ok(project_info.metadata.current)
+    Ok(get_purpur_project_info()?.metadata.current)
 }
 
-fn get_purpur(version: Option<String>) -> Result<String> {
-    let version = version.map_or_else(get_current_purpur_version, Ok)?;
+fn get_latest_purpur_build(version: &str) -> Result<String> {
+    Ok(get_purpur_version_info(version)?.builds.latest)
+}
 
-    let version_url = format!("{PURPUR_BASE_API_URL}/{version}");
-    let version_info: PurpurVersionInfo = blocking::get(&version_url)?.json()?;
+fn get_purpur(version: Option<String>) -> Result<Download> {
+    let version = version.map_or_else(get_current_purpur_version, Ok)?;
 
-    let latest = version_info.builds.latest;
+    let latest = get_latest_purpur_build(&version)?;
     println!("Creating purpur server (v{version}, build {latest})");
 
-    let download_url = format!("{version_url}/{latest}/download");
-    Ok(download_url)
+    let build_url = format!("{PURPUR_BASE_API_URL}/{version}/{latest}");
+    let build_info: PurpurBuildInfo = blocking::get(&build_url)?.json()?;
+
+    Ok(Download {
+        url: Url::parse(&format!("{build_url}/download"))?,
+        version,
+        build: Some(latest),
+        checksum: Some(Checksum::new(Algorithm::Md5, build_info.md5)),
+        java_major: None,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct ForgePromotions {
+    promos: HashMap<String, String>,
+}
+
+// versions that aren't purely numeric, such as pre-releases, are never considered the latest
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+    version.split('.').map(|part| part.parse().ok()).collect()
+}
+
+fn get_forge_promotions() -> Result<ForgePromotions> {
+    Ok(get_client()?.get(FORGE_PROMOTIONS_URL).send()?.json()?)
+}
+
+fn get_latest_forge_version(promotions: &ForgePromotions) -> Result<String> {
+    promotions
+        .promos
+        .keys()
+        .filter_map(|key| key.rsplit_once('-').map(|(version, _)| version))
+        .filter_map(|version| parse_version(version).map(|parsed| (parsed, version)))
+        .max()
+        .map(|(_, version)| version.to_string())
+        .ok_or_else(|| Error::PlatformsNotFound("forge version".to_string()))
+}
+
+// recommended builds are preferred, but newer game versions often only have a latest build
+fn get_forge_build(promotions: &ForgePromotions, version: &str) -> Result<String> {
+    promotions
+        .promos
+        .get(&format!("{version}-recommended"))
+        .or_else(|| promotions.promos.get(&format!("{version}-latest")))
+        .cloned()
+        .ok_or_else(|| Error::PlatformsNotFound(format!("forge build for {version}")))
+}
+
+// maven publishes a sha1 next to every artifact
+fn get_maven_sha1(url: &str) -> Result<Option<Checksum>> {
+    let response = get_client()?.get(format!("{url}.sha1")).send()?;
+
+    if !response.status().is_success() {
+        return Ok(None);
+    }
+
+    Ok(Some(Checksum::new(
+        Algorithm::Sha1,
+        response.text()?.trim(),
+    )))
+}
+
+// forge is distributed as an installer, which is run in the server directory
+fn get_forge(version: Option<String>) -> Result<Download> {
+    let promotions = get_forge_promotions()?;
+    let version = version.map_or_else(|| get_latest_forge_version(&promotions), Ok)?;
+    let build = get_forge_build(&promotions, &version)?;
+
+    let artifact = format!("{version}-{build}");
+    let url = format!("{FORGE_MAVEN_URL}/{artifact}/forge-{artifact}-installer.jar");
+
+    Ok(Download {
+        checksum: get_maven_sha1(&url)?,
+        url: Url::parse(&url)?,
+        version,
+        build: Some(build),
+        java_major: None,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct NeoforgeVersions {
+    versions: Vec<String>,
+}
+
+struct NeoforgeBuild {
+    game_version: String,
+    build: String,
+    numbers: Vec<u32>,
+    stable: bool,
+}
+
+// neoforge versions encode the game version, 21.1.77 is for 1.21.1 while year based releases
+// such as 26.1.0.3 keep the full game version in front of the build number
+fn parse_neoforge_build(build: String) -> Option<NeoforgeBuild> {
+    let (numbers, stable) = match build.split_once('-') {
+        Some((numbers, _)) => (numbers, false),
+        None => (build.as_str(), true),
+    };
+    let numbers = parse_version(numbers)?;
+
+    let game_version = match numbers.as_slice() {
+        [major, minor, _] if *major < 26 => match minor {
+            0 => format!("1.{major}"),
+            minor => format!("1.{major}.{minor}"),
+        },
+        [major, minor, patch, _] => match patch {
+            0 => format!("{major}.{minor}"),
+            patch => format!("{major}.{minor}.{patch}"),
+        },
+        _ => return None,
+    };
+
+    Some(NeoforgeBuild {
+        game_version,
+        build,
+        numbers,
+        stable,
+    })
+}
+
+fn get_neoforge_builds() -> Result<Vec<NeoforgeBuild>> {
+    let versions: NeoforgeVersions = get_client()?.get(NEOFORGE_VERSIONS_URL).send()?.json()?;
+
+    let mut builds: Vec<_> = versions
+        .versions
+        .into_iter()
+        .filter_map(parse_neoforge_build)
+        .collect();
+    builds.sort_by(|a, b| a.numbers.cmp(&b.numbers));
+
+    Ok(builds)
+}
+
+fn get_latest_neoforge_version(builds: &[NeoforgeBuild]) -> Result<String> {
+    builds
+        .iter()
+        .rfind(|build| build.stable)
+        .map(|build| build.game_version.clone())
+        .ok_or_else(|| Error::PlatformsNotFound("stable neoforge version".to_string()))
+}
+
+// stable builds are preferred, but snapshots of the game only ever get beta builds
+fn get_neoforge_build(builds: &[NeoforgeBuild], version: &str) -> Result<String> {
+    let mut candidates = builds.iter().filter(|build| build.game_version == version);
+
+    candidates
+        .clone()
+        .rfind(|build| build.stable)
+        .or_else(|| candidates.next_back())
+        .map(|build| build.build.clone())
+        .ok_or_else(|| Error::PlatformsNotFound(format!("neoforge build for {version}")))
+}
+
+fn get_neoforge(version: Option<String>) -> Result<Download> {
+    let builds = get_neoforge_builds()?;
+    let version = version.map_or_else(|| get_latest_neoforge_version(&builds), Ok)?;
+    let build = get_neoforge_build(&builds, &version)?;
+
+    let url = format!("{NEOFORGE_MAVEN_URL}/{build}/neoforge-{build}-installer.jar");
+
+    Ok(Download {
+        checksum: get_maven_sha1(&url)?,
+        url: Url::parse(&url)?,
+        version,
+        build: Some(build),
+        java_major: None,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct VanillaManifest {
+    latest: VanillaLatest,
+    versions: Vec<VanillaVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VanillaLatest {
+    release: String,
+    snapshot: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct VanillaVersion {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    sha1: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VanillaVersionInfo {
+    downloads: VanillaDownloads,
+    java_version: Option<VanillaJavaVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VanillaDownloads {
+    server: Option<VanillaDownload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct VanillaDownload {
+    url: String,
+    sha1: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VanillaJavaVersion {
+    major_version: u32,
+}
+
+fn get_vanilla_manifest() -> Result<VanillaManifest> {
+    Ok(get_client()?.get(VANILLA_MANIFEST_URL).send()?.json()?)
+}
+
+// the manifest pins the hash of every version json, so it's checked before trusting its urls
+fn get_vanilla_version_info(version: &VanillaVersion) -> Result<VanillaVersionInfo> {
+    let bytes = get_client()?
+        .get(&version.url)
+        .send()?
+        .error_for_status()?
+        .bytes()?;
+
+    let expected = Checksum::new(Algorithm::Sha1, &version.sha1);
+    let actual = checksum::copy(&bytes[..], io::sink(), Algorithm::Sha1)?;
+    if expected != actual {
+        return Err(Error::ChecksumMismatch {
+            file: format!("{}.json", version.id),
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        });
+    }
+
+    Ok(serde_json::from_slice(&bytes)?)
+}
+
+// `release` and `snapshot` select the newest version of that kind, anything else is a version id
+fn get_vanilla(version: Option<String>) -> Result<Download> {
+    let manifest = get_vanilla_manifest()?;
+
+    let version = match version.as_deref() {
+        None | Some("release") => manifest.latest.release,
+        Some("snapshot") => manifest.latest.snapshot,
+        Some(_) => version.unwrap_or_default(),
+    };
+
+    let entry = manifest
+        .versions
+        .iter()
+        .find(|entry| entry.id == version)
+        .ok_or_else(|| Error::PlatformsNotFound(format!("vanilla version {version}")))?;
+    let info = get_vanilla_version_info(entry)?;
+
+    let server = info
+        .downloads
+        .server
+        .ok_or_else(|| Error::PlatformsNotFound(format!("vanilla server jar for {version}")))?;
+
+    Ok(Download {
+        url: Url::parse(&server.url)?,
+        version,
+        build: None,
+        checksum: Some(Checksum::new(Algorithm::Sha1, server.sha1)),
+        java_major: info.java_version.map(|java| java.major_version),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct QuiltGameVersion {
+    version: String,
+    stable: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct QuiltVersion {
+    version: String,
+}
+
+fn get_quilt_versions<T: DeserializeOwned>(kind: &str) -> Result<Vec<T>> {
+    Ok(get_client()?
+        .get(format!("{QUILT_META_URL}/{kind}"))
+        .send()?
+        .json()?)
+}
+
+fn get_latest_quilt_version() -> Result<String> {
+    get_quilt_versions::<QuiltGameVersion>("game")?
+        .into_iter()
+        .find(|version| version.stable)
+        .map(|version| version.version)
+        .ok_or_else(|| Error::PlatformsNotFound("stable quilt game version".to_string()))
+}
+
+// the quilt meta api lists the newest versions first, and marks betas in the version itself
+fn get_latest_quilt_loader() -> Result<String> {
+    get_quilt_versions::<QuiltVersion>("loader")?
+        .into_iter()
+        .map(|loader| loader.version)
+        .find(|version| !version.contains('-'))
+        .ok_or_else(|| Error::PlatformsNotFound("stable quilt loader".to_string()))
+}
+
+// quilt is installed with its installer, which also downloads the vanilla server jar
+fn get_quilt(version: Option<String>) -> Result<Download> {
+    let version = version.map_or_else(get_latest_quilt_version, Ok)?;
+    let loader = get_latest_quilt_loader()?;
+    let installer = get_quilt_versions::<QuiltVersion>("installer")?
+        .into_iter()
+        .next()
+        .map(|installer| installer.version)
+        .ok_or_else(|| Error::PlatformsNotFound("quilt installer".to_string()))?;
+
+    let url = format!("{QUILT_INSTALLER_MAVEN_URL}/{installer}/quilt-installer-{installer}.jar");
+
+    Ok(Download {
+        checksum: get_maven_sha1(&url)?,
+        url: Url::parse(&url)?,
+        version,
+        build: Some(loader),
+        java_major: None,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SpigotVersionInfo {
+    name: String,
+    java_versions: Option<[u32; 2]>,
+}
+
+fn get_spigot_version_info(version: &str) -> Result<Option<SpigotVersionInfo>> {
+    let response = get_client()?
+        .get(format!("{SPIGOT_VERSIONS_URL}/{version}.json"))
+        .send()?;
+
+    if response.status() == StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+
+    Ok(Some(response.error_for_status()?.json()?))
+}
+
+// spigot doesn't list its versions, so the newest game releases are tried until one has been built
+fn get_latest_spigot_version() -> Result<String> {
+    for entry in get_vanilla_manifest()?
+        .versions
+        .into_iter()
+        .filter(|entry| entry.kind == "release")
+        .take(SPIGOT_LOOKBACK)
+    {
+        if get_spigot_version_info(&entry.id)?.is_some() {
+            return Ok(entry.id);
+        }
+    }
+
+    Err(Error::PlatformsNotFound("spigot version".to_string()))
+}
+
+fn get_spigot_build(version: &str) -> Result<SpigotVersionInfo> {
+    get_spigot_version_info(version)?
+        .ok_or_else(|| Error::PlatformsNotFound(format!("spigot build for {version}")))
+}
+
+// spigot can't be redistributed, so buildtools compiles it with the oldest java the build supports
+fn get_spigot(version: Option<String>) -> Result<Download> {
+    let version = version.map_or_else(get_latest_spigot_version, Ok)?;
+    let info = get_spigot_build(&version)?;
+
+    Ok(Download {
+        url: Url::parse(BUILDTOOLS_URL)?,
+        version,
+        build: Some(info.name),
+        checksum: None,
+        java_major: info
+            .java_versions
+            .map(|[oldest, _]| oldest.saturating_sub(CLASS_FILE_OFFSET)),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct JenkinsBuild {
+    number: u32,
+}
+
+fn get_latest_bungeecord_build() -> Result<String> {
+    let build: JenkinsBuild = get_client()?
+        .get(format!("{BUNGEECORD_JOB_URL}/lastSuccessfulBuild/api/json"))
+        .send()?
+        .error_for_status()?
+        .json()?;
+    Ok(build.number.to_string())
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, ValueEnum, Debug)]
+// bungeecord supports every game version at once, so its jenkins build doubles as the version
+fn get_bungeecord(version: Option<String>) -> Result<Download> {
+    let build = version.map_or_else(get_latest_bungeecord_build, Ok)?;
+
+    Ok(Download {
+        url: Url::parse(&format!(
+            "{BUNGEECORD_JOB_URL}/{build}/artifact/bootstrap/target/BungeeCord.jar"
+        ))?,
+        version: build.clone(),
+        build: Some(build),
+        checksum: None,
+        java_major: None,
+    })
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
 pub enum Platform {
+    Bungeecord,
     Fabric,
+    Folia,
     Forge,
     Neoforge,
     Paper,
     Purpur,
+    Quilt,
+    Spigot,
+    Vanilla,
+    Velocity,
+    Waterfall,
+    // a provider defined in the config
+    Custom(String),
 }
 
+const BUILTIN_PLATFORMS: [Platform; 12] = [
+    Platform::Bungeecord,
+    Platform::Fabric,
+    Platform::Folia,
+    Platform::Forge,
+    Platform::Neoforge,
+    Platform::Paper,
+    Platform::Purpur,
+    Platform::Quilt,
+    Platform::Spigot,
+    Platform::Vanilla,
+    Platform::Velocity,
+    Platform::Waterfall,
+];
+
 impl Display for Platform {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
+            Self::Bungeecord => write!(f, "bungeecord"),
             Self::Fabric => write!(f, "fabric"),
+            Self::Folia => write!(f, "folia"),
             Self::Forge => write!(f, "forge"),
             Self::Neoforge => write!(f, "neoforge"),
             Self::Paper => write!(f, "paper"),
             Self::Purpur => write!(f, "purpur"),
+            Self::Quilt => write!(f, "quilt"),
+            Self::Spigot => write!(f, "spigot"),
+            Self::Vanilla => write!(f, "vanilla"),
+            Self::Velocity => write!(f, "velocity"),
+            Self::Waterfall => write!(f, "waterfall"),
+            Self::Custom(name) => write!(f, "{name}"),
+        }
+    }
+}
+
+// built-in names take precedence over custom providers with the same name
+impl TryFrom<String> for Platform {
+    type Error = String;
+
+    fn try_from(name: String) -> std::result::Result<Self, Self::Error> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!("invalid platform name `{name}`"));
+        }
+
+        let name = name.to_lowercase();
+
+        Ok(BUILTIN_PLATFORMS
+            .into_iter()
+            .find(|platform| platform.to_string() == name)
+            .unwrap_or(Self::Custom(name)))
+    }
+}
+
+impl From<Platform> for String {
+    fn from(platform: Platform) -> Self {
+        platform.to_string()
+    }
+}
+
+impl FromStr for Platform {
+    type Err = String;
+
+    fn from_str(name: &str) -> std::result::Result<Self, Self::Err> {
+        Self::try_from(name.to_string())
+    }
+}
+
+// everything that differs between platforms, from finding a version to starting the server
+pub trait PlatformProvider {
+    // installable versions, the one installed by default first
+    fn versions(&self) -> Result<Vec<String>>;
+
+    // builds of a version, the one installed by default first, empty when it isn't rebuilt
+    fn builds(&self, version: &str) -> Result<Vec<String>>;
+
+    // where a version is downloaded from, with the checksum it's published with
+    fn download(&self, version: Option<String>) -> Result<Download>;
+
+    fn latest_version(&self) -> Result<Option<String>> {
+        Ok(self.versions()?.into_iter().next())
+    }
+
+    fn latest_build(&self, version: &str) -> Result<Option<String>> {
+        Ok(self.builds(version)?.into_iter().next())
+    }
+
+    fn resolve(&self, version: Option<String>) -> Result<Download> {
+        // set version to none if the it is "latest" so that it defaults to the latest one
+        self.download(version.filter(|v| v != "latest"))
+    }
+
+    // installer based platforms install the server from the download, which is otherwise copied
+    // into the server directory as is
+    fn install(
+        &self,
+        _download: &Download,
+        _jar: &CachedJar,
+        _server_dir: &Path,
+    ) -> Result<Option<Installed>> {
+        Ok(None)
+    }
+
+    // runs once the server directory holds the new jar
+    fn post_install(&self, _server_dir: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    // how a copied jar is started, installers detect this themselves
+    fn launch(&self) -> Launch {
+        Launch::Jar
+    }
+
+    fn is_proxy(&self) -> bool {
+        false
+    }
+}
+
+struct Bungeecord;
+
+impl PlatformProvider for Bungeecord {
+    fn versions(&self) -> Result<Vec<String>> {
+        Ok(vec![get_latest_bungeecord_build()?])
+    }
+
+    fn builds(&self, version: &str) -> Result<Vec<String>> {
+        Ok(vec![version.to_string()])
+    }
+
+    fn download(&self, version: Option<String>) -> Result<Download> {
+        get_bungeecord(version)
+    }
+
+    fn is_proxy(&self) -> bool {
+        true
+    }
+}
+
+struct Fabric;
+
+fn stable_fabric_versions(entries: Vec<FabricEntry>) -> Vec<String> {
+    entries
+        .into_iter()
+        .filter(|entry| entry.stable)
+        .map(|entry| entry.version)
+        .collect()
+}
+
+impl PlatformProvider for Fabric {
+    fn versions(&self) -> Result<Vec<String>> {
+        Ok(stable_fabric_versions(get_fabric_versions()?.game))
+    }
+
+    // loaders aren't tied to a game version
+    fn builds(&self, _version: &str) -> Result<Vec<String>> {
+        Ok(stable_fabric_versions(get_fabric_versions()?.loader))
+    }
+
+    fn download(&self, version: Option<String>) -> Result<Download> {
+        get_fabric(version)
+    }
+}
+
+// every project on the paper api, which includes folia, velocity and waterfall
+struct Paper(Platform);
+
+impl PlatformProvider for Paper {
+    fn versions(&self) -> Result<Vec<String>> {
+        let mut versions = get_paper_versions(&self.0)?;
+        versions.reverse();
+        Ok(versions)
+    }
+
+    fn builds(&self, version: &str) -> Result<Vec<String>> {
+        Ok(get_paper_builds(&self.0, version)?
+            .into_iter()
+            .rev()
+            .map(|build| build.build.to_string())
+            .collect())
+    }
+
+    fn download(&self, version: Option<String>) -> Result<Download> {
+        get_paper(&self.0, version)
+    }
+
+    fn is_proxy(&self) -> bool {
+        matches!(self.0, Platform::Velocity | Platform::Waterfall)
+    }
+}
+
+struct Purpur;
+
+// the current version and latest build are listed first, the others newest first
+fn current_first(mut all: Vec<String>, current: String) -> Vec<String> {
+    all.retain(|version| *version != current);
+    all.reverse();
+    all.insert(0, current);
+    all
+}
+
+impl PlatformProvider for Purpur {
+    fn versions(&self) -> Result<Vec<String>> {
+        let project_info = get_purpur_project_info()?;
+        Ok(current_first(
+            project_info.versions,
+            project_info.metadata.current,
+        ))
+    }
+
+    fn builds(&self, version: &str) -> Result<Vec<String>> {
+        let builds = get_purpur_version_info(version)?.builds;
+        Ok(current_first(builds.all, builds.latest))
+    }
+
+    fn download(&self, version: Option<String>) -> Result<Download> {
+        get_purpur(version)
+    }
+}
+
+fn install_with(
+    platform: Platform,
+    download: &Download,
+    jar: &CachedJar,
+    server_dir: &Path,
+) -> Result<Option<Installed>> {
+    installer::install(&platform, download, jar, server_dir).map(Some)
+}
+
+struct Forge;
+
+impl PlatformProvider for Forge {
+    fn versions(&self) -> Result<Vec<String>> {
+        let mut versions: Vec<_> = get_forge_promotions()?
+            .promos
+            .into_keys()
+            .filter_map(|key| key.rsplit_once('-').map(|(version, _)| version.to_string()))
+            .filter_map(|version| parse_version(&version).map(|parsed| (parsed, version)))
+            .collect();
+        versions.sort();
+        versions.dedup();
+
+        Ok(versions
+            .into_iter()
+            .rev()
+            .map(|(_, version)| version)
+            .collect())
+    }
+
+    fn builds(&self, version: &str) -> Result<Vec<String>> {
+        let promotions = get_forge_promotions()?;
+        let mut builds: Vec<String> = ["recommended", "latest"]
+            .into_iter()
+            .filter_map(|kind| promotions.promos.get(&format!("{version}-{kind}")).cloned())
+            .collect();
+        builds.dedup();
+        Ok(builds)
+    }
+
+    fn download(&self, version: Option<String>) -> Result<Download> {
+        get_forge(version)
+    }
+
+    fn install(
+        &self,
+        download: &Download,
+        jar: &CachedJar,
+        server_dir: &Path,
+    ) -> Result<Option<Installed>> {
+        install_with(Platform::Forge, download, jar, server_dir)
+    }
+}
+
+struct Neoforge;
+
+impl PlatformProvider for Neoforge {
+    // versions with a stable build come first
+    fn versions(&self) -> Result<Vec<String>> {
+        let builds = get_neoforge_builds()?;
+        let mut versions: Vec<(bool, String)> = vec![];
+
+        for build in builds.into_iter().rev() {
+            match versions
+                .iter_mut()
+                .find(|(_, version)| *version == build.game_version)
+            {
+                Some((stable, _)) => *stable |= build.stable,
+                None => versions.push((build.stable, build.game_version)),
+            }
+        }
+        versions.sort_by_key(|(stable, _)| !stable);
+
+        Ok(versions.into_iter().map(|(_, version)| version).collect())
+    }
+
+    fn builds(&self, version: &str) -> Result<Vec<String>> {
+        let mut builds: Vec<_> = get_neoforge_builds()?
+            .into_iter()
+            .rev()
+            .filter(|build| build.game_version == version)
+            .collect();
+        builds.sort_by_key(|build| !build.stable);
+
+        Ok(builds.into_iter().map(|build| build.build).collect())
+    }
+
+    fn download(&self, version: Option<String>) -> Result<Download> {
+        get_neoforge(version)
+    }
+
+    fn install(
+        &self,
+        download: &Download,
+        jar: &CachedJar,
+        server_dir: &Path,
+    ) -> Result<Option<Installed>> {
+        install_with(Platform::Neoforge, download, jar, server_dir)
+    }
+}
+
+struct Quilt;
+
+impl PlatformProvider for Quilt {
+    fn versions(&self) -> Result<Vec<String>> {
+        Ok(get_quilt_versions::<QuiltGameVersion>("game")?
+            .into_iter()
+            .filter(|version| version.stable)
+            .map(|version| version.version)
+            .collect())
+    }
+
+    // loaders aren't tied to a game version
+    fn builds(&self, _version: &str) -> Result<Vec<String>> {
+        Ok(get_quilt_versions::<QuiltVersion>("loader")?
+            .into_iter()
+            .map(|loader| loader.version)
+            .filter(|version| !version.contains('-'))
+            .collect())
+    }
+
+    fn download(&self, version: Option<String>) -> Result<Download> {
+        get_quilt(version)
+    }
+
+    fn install(
+        &self,
+        download: &Download,
+        jar: &CachedJar,
+        server_dir: &Path,
+    ) -> Result<Option<Installed>> {
+        install_with(Platform::Quilt, download, jar, server_dir)
+    }
+}
+
+struct Spigot;
+
+impl PlatformProvider for Spigot {
+    fn versions(&self) -> Result<Vec<String>> {
+        let mut versions = vec![];
+
+        for entry in get_vanilla_manifest()?
+            .versions
+            .into_iter()
+            .filter(|entry| entry.kind == "release")
+            .take(SPIGOT_LOOKBACK)
+        {
+            if get_spigot_version_info(&entry.id)?.is_some() {
+                versions.push(entry.id);
+            }
         }
+
+        Ok(versions)
+    }
+
+    fn builds(&self, version: &str) -> Result<Vec<String>> {
+        Ok(vec![get_spigot_build(version)?.name])
+    }
+
+    fn download(&self, version: Option<String>) -> Result<Download> {
+        get_spigot(version)
+    }
+
+    // stops at the first version that has been built
+    fn latest_version(&self) -> Result<Option<String>> {
+        get_latest_spigot_version().map(Some)
+    }
+
+    fn install(
+        &self,
+        download: &Download,
+        jar: &CachedJar,
+        server_dir: &Path,
+    ) -> Result<Option<Installed>> {
+        install_with(Platform::Spigot, download, jar, server_dir)
     }
 }
 
-pub fn get(platform: Platform, version: Option<String>) -> Result<Url> {
-    // set version to none if the it is "latest" so that it defaults to the latest one
-    let version = version.filter(|v| v != "latest");
+struct Vanilla;
+
+impl PlatformProvider for Vanilla {
+    // snapshots are listed, but the latest release comes first
+    fn versions(&self) -> Result<Vec<String>> {
+        let manifest = get_vanilla_manifest()?;
+        Ok(current_first(
+            manifest
+                .versions
+                .into_iter()
+                .rev()
+                .map(|version| version.id)
+                .collect(),
+            manifest.latest.release,
+        ))
+    }
+
+    // vanilla versions are never rebuilt
+    fn builds(&self, _version: &str) -> Result<Vec<String>> {
+        Ok(vec![])
+    }
+
+    fn download(&self, version: Option<String>) -> Result<Download> {
+        get_vanilla(version)
+    }
+}
+
+pub fn get_provider(platform: &Platform) -> Result<Box<dyn PlatformProvider>> {
+    Ok(match platform {
+        Platform::Bungeecord => Box::new(Bungeecord),
+        Platform::Fabric => Box::new(Fabric),
+        Platform::Folia | Platform::Paper | Platform::Velocity | Platform::Waterfall => {
+            Box::new(Paper(platform.clone()))
+        }
+        Platform::Forge => Box::new(Forge),
+        Platform::Neoforge => Box::new(Neoforge),
+        Platform::Purpur => Box::new(Purpur),
+        Platform::Quilt => Box::new(Quilt),
+        Platform::Spigot => Box::new(Spigot),
+        Platform::Vanilla => Box::new(Vanilla),
+        Platform::Custom(name) => Box::new(providers::get(name)?),
+    })
+}
 
-    let download_url = match platform {
-        Platform::Fabric => get_fabric(version)?,
-        Platform::Forge => todo!(),
-        Platform::Neoforge => todo!(),
-        Platform::Paper => get_paper(version)?,
-        Platform::Purpur => get_purpur(version)?,
+// servers of a custom platform that was removed from the config are treated as regular servers
+pub fn is_proxy(platform: &Platform) -> bool {
+    get_provider(platform).is_ok_and(|provider| provider.is_proxy())
+}
+
+pub fn list() -> Result<()> {
+    let mut platforms = BUILTIN_PLATFORMS.to_vec();
+
+    for name in providers::names()? {
+        match Platform::try_from(name) {
+            Ok(Platform::Custom(name)) => platforms.push(Platform::Custom(name)),
+            Ok(platform) => {
+                eprintln!("Warning: the custom {platform} provider is shadowed by the built-in one")
+            }
+            Err(err) => eprintln!("Warning: the custom provider has an {err}"),
+        }
+    }
+
+    for platform in platforms {
+        let mut tags = vec![];
+        if matches!(platform, Platform::Custom(_)) {
+            tags.push("custom");
+        }
+        if is_proxy(&platform) {
+            tags.push("proxy");
+        }
+
+        if tags.is_empty() {
+            println!("{platform}");
+        } else {
+            println!("{platform} ({})", tags.join(", "));
+        }
+    }
+
+    Ok(())
+}
+
+// lists the versions of a platform, or the builds of one of its versions
+pub fn list_versions(platform: &Platform, version: Option<String>) -> Result<()> {
+    let provider = get_provider(platform)?;
+
+    let entries = match &version {
+        Some(version) => provider.builds(version)?,
+        None => provider.versions()?,
     };
 
-    Ok(Url::parse(&download_url)?)
+    if entries.is_empty() {
+        match version {
+            Some(version) => println!("{platform} {version} has no builds"),
+            None => println!("{platform} has no versions"),
+        }
+    }
+
+    for entry in entries {
+        println!("{entry}");
+    }
+
+    Ok(())
 }
@@ -0,0 +1,243 @@
+use crate::{
+    config::{self, get_expanded_servers_dir},
+    error::{Error, Result},
+    properties::{self, Properties},
+    server::{self, ServerObject},
+    session,
+};
+use std::{
+    collections::HashSet,
+    fmt::{self, Display, Formatter},
+    net::{TcpListener, UdpSocket},
+    path::{Path, PathBuf},
+};
+
+const SERVER_PORT: &str = "server-port";
+const RCON_PORT: &str = "rcon.port";
+const QUERY_PORT: &str = "query.port";
+
+// matches the distance between the default server-port and rcon.port
+const RCON_OFFSET: u16 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Protocol {
+    Tcp,
+    Udp,
+}
+
+impl Display for Protocol {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp => write!(f, "tcp"),
+            Self::Udp => write!(f, "udp"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct AllocatedPort {
+    pub server: String,
+    pub key: &'static str,
+    pub port: u16,
+    protocol: Protocol,
+}
+
+fn protocol(key: &str) -> Protocol {
+    if key == QUERY_PORT {
+        Protocol::Udp
+    } else {
+        Protocol::Tcp
+    }
+}
+
+fn read_properties(server_dir: &Path) -> Result<Properties> {
+    let path = server_dir.join(properties::FILE_NAME);
+
+    if path.is_file() {
+        Properties::read(path)
+    } else {
+        Ok(Properties::default())
+    }
+}
+
+fn get_port(properties: &Properties, key: &str) -> Option<u16> {
+    properties
+        .get(key)
+        .map_or_else(
+            || properties::get_schema(key).map(|schema| schema.default),
+            Some,
+        )
+        .and_then(|port| port.parse().ok())
+}
+
+// only the ports that the server actually binds are returned
+fn server_ports(server: &str, server_dir: &Path) -> Result<Vec<AllocatedPort>> {
+    let properties = read_properties(server_dir)?;
+
+    let mut keys = vec![SERVER_PORT];
+    if properties.get("enable-rcon") == Some("true") {
+        keys.push(RCON_PORT);
+    }
+    if properties.get("enable-query") == Some("true") {
+        keys.push(QUERY_PORT);
+    }
+
+    Ok(keys
+        .into_iter()
+        .filter_map(|key| {
+            get_port(&properties, key).map(|port| AllocatedPort {
+                server: server.to_string(),
+                key,
+                port,
+                protocol: protocol(key),
+            })
+        })
+        .collect())
+}
+
+fn get_server_dirs() -> Result<Vec<(String, PathBuf)>> {
+    let servers_dir = get_expanded_servers_dir()?;
+
+    let mut servers = vec![];
+    server::for_each(|server| {
+        let server_dir = servers_dir.join(&server);
+        if server_dir.is_dir() {
+            servers.push((server, server_dir));
+        }
+    })?;
+
+    Ok(servers)
+}
+
+pub fn scan() -> Result<Vec<AllocatedPort>> {
+    let mut ports = vec![];
+
+    for (server, server_dir) in get_server_dirs()? {
+        ports.extend(server_ports(&server, &server_dir)?);
+    }
+
+    Ok(ports)
+}
+
+pub fn get_conflicts(server: &str, ports: &[AllocatedPort]) -> Vec<(AllocatedPort, AllocatedPort)> {
+    let own = ports.iter().filter(|port| port.server == server);
+
+    own.flat_map(|own| {
+        ports
+            .iter()
+            .filter(move |other| {
+                other.server != server && other.port == own.port && other.protocol == own.protocol
+            })
+            .map(move |other| (own.clone(), other.clone()))
+    })
+    .collect()
+}
+
+pub fn tag_conflicting_servers(servers: &mut [ServerObject]) -> Result<()> {
+    let ports = scan()?;
+
+    for server in servers {
+        let conflicts = get_conflicts(&server.name, &ports);
+
+        if conflicts.is_empty() {
+            continue;
+        }
+
+        let description = conflicts
+            .iter()
+            .map(|(own, other)| format!("{} {} with {}", own.key, own.port, other.server))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        server
+            .tags
+            .push(format!("(\x1b[31;1mport conflict\x1b[0m: {description})"));
+    }
+
+    Ok(())
+}
+
+pub fn check_deployable(server: &str) -> Result<()> {
+    let conflicts = get_conflicts(server, &scan()?);
+
+    for (own, other) in conflicts {
+        if session::is_active(&other.server)? {
+            return Err(Error::PortConflict {
+                server: other.server,
+                key: own.key.to_string(),
+                port: own.port,
+            });
+        }
+
+        eprintln!(
+            "Warning: {} {} is also used by {} ({})",
+            own.key, own.port, other.server, other.key
+        );
+    }
+
+    Ok(())
+}
+
+fn is_bindable(port: u16, protocol: Protocol) -> bool {
+    match protocol {
+        Protocol::Tcp => TcpListener::bind(("0.0.0.0", port)).is_ok(),
+        Protocol::Udp => UdpSocket::bind(("0.0.0.0", port)).is_ok(),
+    }
+}
+
+// assigns a free server port from the configured range, and the rcon and query ports beside it, to a
+// server that isn't running
+pub fn allocate(server: &str, server_dir: &Path) -> Result<()> {
+    let range = config::get()?.port_range.clone();
+
+    // every declared port is avoided, including those of disabled rcon or query listeners
+    let mut used: HashSet<(u16, Protocol)> = HashSet::new();
+    for (other, other_dir) in get_server_dirs()? {
+        if other == server {
+            continue;
+        }
+
+        let properties = read_properties(&other_dir)?;
+        for key in [SERVER_PORT, RCON_PORT, QUERY_PORT] {
+            if let Some(port) = get_port(&properties, key) {
+                used.insert((port, protocol(key)));
+            }
+        }
+    }
+
+    // only server-port comes from the range, rcon and query sit beside it like the vanilla defaults
+    let mut properties = read_properties(server_dir)?;
+    let ports = (range.start..=range.end)
+        .filter_map(|port| {
+            Some([
+                (SERVER_PORT, port),
+                (RCON_PORT, port.checked_add(RCON_OFFSET)?),
+                (QUERY_PORT, port),
+            ])
+        })
+        .find(|ports| {
+            ports.iter().all(|&(key, port)| {
+                let protocol = protocol(key);
+                !used.contains(&(port, protocol)) && is_bindable(port, protocol)
+            })
+        })
+        .ok_or(Error::NoFreePorts {
+            start: range.start,
+            end: range.end,
+        })?;
+
+    for (key, port) in ports {
+        properties.set(key, port.to_string());
+        println!("Assigned {} port {port} to {key}", protocol(key));
+    }
+
+    properties.write(server_dir.join(properties::FILE_NAME))?;
+
+    if let Some(port) = get_port(&properties, RCON_PORT)
+        && let Some(rcon) = config::get()?.rcon.get_mut(server)
+    {
+        rcon.port = Some(port);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use std::{
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+const BAR_WIDTH: usize = 30;
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct Progress {
+    total: Option<u64>,
+    done: u64,
+    // bytes that were already present, such as a partial download being resumed
+    initial: u64,
+    started: Instant,
+    last_draw: Option<Instant>,
+}
+
+pub fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+impl Progress {
+    pub fn new(total: Option<u64>, initial: u64) -> Self {
+        Self {
+            total,
+            done: initial,
+            initial,
+            started: Instant::now(),
+            last_draw: None,
+        }
+    }
+
+    pub fn advance(&mut self, bytes: u64) {
+        self.done += bytes;
+
+        if self
+            .last_draw
+            .is_none_or(|last_draw| last_draw.elapsed() >= REDRAW_INTERVAL)
+        {
+            self.draw();
+        }
+    }
+
+    fn draw(&mut self) {
+        self.last_draw = Some(Instant::now());
+
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let speed = if elapsed > 0.0 {
+            (self.done - self.initial) as f64 / elapsed
+        } else {
+            0.0
+        };
+
+        let line = match self.total {
+            Some(total) if total > 0 => {
+                let ratio = (self.done as f64 / total as f64).min(1.0);
+                let filled = (ratio * BAR_WIDTH as f64) as usize;
+                format!(
+                    "[{}{}] {:>3.0}% {}/{} {}/s",
+                    "#".repeat(filled),
+                    "-".repeat(BAR_WIDTH - filled),
+                    ratio * 100.0,
+                    format_bytes(self.done as f64),
+                    format_bytes(total as f64),
+                    format_bytes(speed),
+                )
+            }
+            _ => format!(
+                "{} {}/s",
+                format_bytes(self.done as f64),
+                format_bytes(speed)
+            ),
+        };
+
+        // progress is drawn on stderr so that it never ends up in piped output
+        let mut stderr = io::stderr();
+        let _ = write!(stderr, "\r\x1b[2K{line}");
+        let _ = stderr.flush();
+    }
+
+    pub fn finish(mut self) {
+        self.draw();
+        eprintln!();
+    }
+}
@@ -0,0 +1,502 @@
+use crate::{
+    error::{Error, Result},
+    server::get_server_dir_required,
+    session,
+};
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display, Formatter},
+    fs,
+    path::{Path, PathBuf},
+};
+
+pub const FILE_NAME: &str = "server.properties";
+
+#[derive(Clone, Debug)]
+enum Line {
+    Entry {
+        key: String,
+        value: String,
+        // the original line, kept so that untouched entries are written back verbatim
+        raw: Option<String>,
+    },
+    Other(String),
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Properties {
+    lines: Vec<Line>,
+}
+
+fn unescape(raw: &str) -> String {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some('f') => value.push('\x0c'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(c) => value.push(c),
+                    None => value.push_str(&hex),
+                }
+            }
+            Some(c) => value.push(c),
+            None => {}
+        }
+    }
+
+    value
+}
+
+fn escape(value: &str, is_key: bool) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for (i, c) in value.chars().enumerate() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            '\r' => escaped.push_str("\\r"),
+            '\x0c' => escaped.push_str("\\f"),
+            '=' | ':' | '#' | '!' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            ' ' if is_key || i == 0 => escaped.push_str("\\ "),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn parse_entry(line: &str) -> Option<(String, String)> {
+    let trimmed = line.trim_start();
+
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('!') {
+        return None;
+    }
+
+    let mut escaped = false;
+    let mut key_end = trimmed.len();
+
+    for (i, c) in trimmed.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '=' || c == ':' || c.is_whitespace() {
+            key_end = i;
+            break;
+        }
+    }
+
+    let key = &trimmed[..key_end];
+    let rest = trimmed[key_end..].trim_start();
+    let rest = rest
+        .strip_prefix('=')
+        .or_else(|| rest.strip_prefix(':'))
+        .unwrap_or(rest)
+        .trim_start();
+
+    Some((unescape(key), unescape(rest)))
+}
+
+impl Properties {
+    pub fn parse(contents: &str) -> Self {
+        let lines = contents
+            .lines()
+            .map(|line| match parse_entry(line) {
+                Some((key, value)) => Line::Entry {
+                    key,
+                    value,
+                    raw: Some(line.to_string()),
+                },
+                None => Line::Other(line.to_string()),
+            })
+            .collect();
+
+        Self { lines }
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+
+        if !path.is_file() {
+            return Err(Error::MissingFile {
+                file: path.to_path_buf(),
+            });
+        }
+
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<()> {
+        fs::write(path, self.to_string())?;
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.iter().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    pub fn set(&mut self, key: &str, value: impl Into<String>) {
+        let value = value.into();
+
+        for line in &mut self.lines {
+            if let Line::Entry {
+                key: k,
+                value: v,
+                raw,
+            } = line
+                && k == key
+            {
+                *v = value;
+                *raw = None;
+                return;
+            }
+        }
+
+        self.lines.push(Line::Entry {
+            key: key.to_string(),
+            value,
+            raw: None,
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines.iter().filter_map(|line| match line {
+            Line::Entry { key, value, .. } => Some((key.as_str(), value.as_str())),
+            Line::Other(_) => None,
+        })
+    }
+}
+
+impl Display for Properties {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            match line {
+                Line::Entry {
+                    raw: Some(line), ..
+                }
+                | Line::Other(line) => writeln!(f, "{line}")?,
+                Line::Entry { key, value, .. } => {
+                    writeln!(f, "{}={}", escape(key, true), escape(value, false))?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum PropertyKind {
+    Bool,
+    Int { min: i64, max: i64 },
+    String,
+    Enum(&'static [&'static str]),
+}
+
+#[derive(Debug)]
+pub struct PropertySchema {
+    pub key: &'static str,
+    pub kind: PropertyKind,
+    pub default: &'static str,
+    pub since: Option<&'static str>,
+}
+
+const PORT: PropertyKind = PropertyKind::Int { min: 1, max: 65535 };
+const NON_NEGATIVE: PropertyKind = PropertyKind::Int {
+    min: 0,
+    max: i32::MAX as i64,
+};
+
+macro_rules! schema {
+    ($($key:literal: $kind:expr, $default:literal $(, since $since:literal)?;)*) => {
+        &[$(PropertySchema {
+            key: $key,
+            kind: $kind,
+            default: $default,
+            since: schema!(@since $($since)?),
+        }),*]
+    };
+    (@since $since:literal) => { Some($since) };
+    (@since) => { None };
+}
+
+pub const SCHEMA: &[PropertySchema] = schema! {
+    "accepts-transfers": PropertyKind::Bool, "false", since "1.20.5";
+    "allow-flight": PropertyKind::Bool, "false";
+    "allow-nether": PropertyKind::Bool, "true";
+    "broadcast-console-to-ops": PropertyKind::Bool, "true";
+    "broadcast-rcon-to-ops": PropertyKind::Bool, "true";
+    "bug-report-link": PropertyKind::String, "", since "1.21";
+    "difficulty": PropertyKind::Enum(&["peaceful", "easy", "normal", "hard"]), "easy";
+    "enable-command-block": PropertyKind::Bool, "false";
+    "enable-jmx-monitoring": PropertyKind::Bool, "false", since "1.16";
+    "enable-query": PropertyKind::Bool, "false";
+    "enable-rcon": PropertyKind::Bool, "false";
+    "enable-status": PropertyKind::Bool, "true", since "1.16";
+    "enforce-secure-profile": PropertyKind::Bool, "true", since "1.19";
+    "enforce-whitelist": PropertyKind::Bool, "false";
+    "entity-broadcast-range-percentage": PropertyKind::Int { min: 10, max: 1000 }, "100", since "1.16";
+    "force-gamemode": PropertyKind::Bool, "false";
+    "function-permission-level": PropertyKind::Int { min: 1, max: 4 }, "2", since "1.14.4";
+    "gamemode": PropertyKind::Enum(&["survival", "creative", "adventure", "spectator"]), "survival";
+    "generate-structures": PropertyKind::Bool, "true";
+    "generator-settings": PropertyKind::String, "{}";
+    "hardcore": PropertyKind::Bool, "false";
+    "hide-online-players": PropertyKind::Bool, "false", since "1.18";
+    "initial-disabled-packs": PropertyKind::String, "", since "1.19.3";
+    "initial-enabled-packs": PropertyKind::String, "vanilla", since "1.19.3";
+    "level-name": PropertyKind::String, "world";
+    "level-seed": PropertyKind::String, "";
+    "level-type": PropertyKind::String, "minecraft:normal";
+    "log-ips": PropertyKind::Bool, "true", since "1.20.2";
+    "max-chained-neighbor-updates": PropertyKind::Int { min: -1, max: i32::MAX as i64 }, "1000000", since "1.19";
+    "max-players": NON_NEGATIVE, "20";
+    "max-tick-time": PropertyKind::Int { min: -1, max: i64::MAX }, "60000";
+    "max-world-size": PropertyKind::Int { min: 1, max: 29999984 }, "29999984";
+    "motd": PropertyKind::String, "A Minecraft Server";
+    "network-compression-threshold": PropertyKind::Int { min: -1, max: i32::MAX as i64 }, "256";
+    "online-mode": PropertyKind::Bool, "true";
+    "op-permission-level": PropertyKind::Int { min: 0, max: 4 }, "4";
+    "pause-when-empty-seconds": NON_NEGATIVE, "60", since "1.21.2";
+    "player-idle-timeout": NON_NEGATIVE, "0";
+    "prevent-proxy-connections": PropertyKind::Bool, "false";
+    "pvp": PropertyKind::Bool, "true";
+    "query.port": PORT, "25565";
+    "rate-limit": NON_NEGATIVE, "0";
+    "rcon.password": PropertyKind::String, "";
+    "rcon.port": PORT, "25575";
+    "region-file-compression": PropertyKind::Enum(&["deflate", "lz4", "none"]), "deflate", since "1.20.5";
+    "require-resource-pack": PropertyKind::Bool, "false";
+    "resource-pack": PropertyKind::String, "";
+    "resource-pack-id": PropertyKind::String, "", since "1.20.3";
+    "resource-pack-prompt": PropertyKind::String, "";
+    "resource-pack-sha1": PropertyKind::String, "";
+    "server-ip": PropertyKind::String, "";
+    "server-port": PORT, "25565";
+    "simulation-distance": PropertyKind::Int { min: 3, max: 32 }, "10", since "1.18";
+    "spawn-animals": PropertyKind::Bool, "true";
+    "spawn-monsters": PropertyKind::Bool, "true";
+    "spawn-npcs": PropertyKind::Bool, "true";
+    "spawn-protection": NON_NEGATIVE, "16";
+    "sync-chunk-writes": PropertyKind::Bool, "true", since "1.16";
+    "text-filtering-config": PropertyKind::String, "";
+    "use-native-transport": PropertyKind::Bool, "true";
+    "view-distance": PropertyKind::Int { min: 3, max: 32 }, "10";
+    "white-list": PropertyKind::Bool, "false";
+};
+
+impl PropertyKind {
+    fn describe(&self) -> String {
+        match self {
+            Self::Bool => "true or false".to_string(),
+            Self::Int { min, max } => format!("an integer from {min} to {max}"),
+            Self::String => "a string".to_string(),
+            Self::Enum(values) => format!("one of {}", values.join(", ")),
+        }
+    }
+
+    fn accepts(&self, value: &str) -> bool {
+        match self {
+            Self::Bool => value == "true" || value == "false",
+            Self::Int { min, max } => value
+                .parse::<i64>()
+                .is_ok_and(|value| (*min..=*max).contains(&value)),
+            Self::String => true,
+            Self::Enum(values) => values.contains(&value),
+        }
+    }
+}
+
+fn parse_version(version: &str) -> Option<Vec<u32>> {
+    version.split('.').map(|part| part.parse().ok()).collect()
+}
+
+// None for versions that can't be compared, such as snapshots
+fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    Some(parse_version(a)?.cmp(&parse_version(b)?))
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+
+        for (j, b) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if a == *b {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(current)
+            };
+            previous = current;
+        }
+    }
+
+    row[b.len()]
+}
+
+pub fn get_schema(key: &str) -> Option<&'static PropertySchema> {
+    SCHEMA.iter().find(|schema| schema.key == key)
+}
+
+pub fn validate(key: &str, value: &str, game_version: Option<&str>) -> Result<()> {
+    let Some(schema) = get_schema(key) else {
+        let suggestion = SCHEMA
+            .iter()
+            .map(|schema| (edit_distance(key, schema.key), schema.key))
+            .filter(|(distance, _)| *distance <= 3)
+            .min()
+            .map(|(_, key)| key.to_string());
+
+        return Err(Error::UnknownProperty {
+            key: key.to_string(),
+            suggestion,
+        });
+    };
+
+    if let (Some(since), Some(version)) = (schema.since, game_version)
+        && compare_versions(version, since) == Some(Ordering::Less)
+    {
+        return Err(Error::UnsupportedProperty {
+            key: key.to_string(),
+            since: since.to_string(),
+            version: version.to_string(),
+        });
+    }
+
+    if !schema.kind.accepts(value) {
+        return Err(Error::InvalidPropertyValue {
+            key: key.to_string(),
+            value: value.to_string(),
+            expected: schema.kind.describe(),
+        });
+    }
+
+    Ok(())
+}
+
+pub fn defaults(game_version: Option<&str>) -> impl Iterator<Item = &'static PropertySchema> {
+    SCHEMA
+        .iter()
+        .filter(move |schema| match (schema.since, game_version) {
+            (Some(since), Some(version)) => {
+                compare_versions(version, since) != Some(Ordering::Less)
+            }
+            _ => true,
+        })
+}
+
+fn get_path(server: &str) -> Result<PathBuf> {
+    Ok(get_server_dir_required(server)?.join(FILE_NAME))
+}
+
+pub fn get_value(server: impl AsRef<str>, key: impl AsRef<str>) -> Result<String> {
+    let key = key.as_ref();
+
+    Properties::read(get_path(server.as_ref())?)?
+        .get(key)
+        .map(String::from)
+        .ok_or_else(|| Error::PropertyNotFound(key.to_string()))
+}
+
+pub fn set_value(
+    server: impl AsRef<str>,
+    key: impl AsRef<str>,
+    value: impl AsRef<str>,
+    game_version: Option<&str>,
+    force: bool,
+) -> Result<()> {
+    let (server, key, value) = (server.as_ref(), key.as_ref(), value.as_ref());
+
+    if let Err(err) = validate(key, value, game_version) {
+        if !force {
+            return Err(err);
+        }
+        eprintln!("Warning: {err}");
+    }
+
+    let path = get_path(server)?;
+    let mut properties = if path.exists() {
+        Properties::read(&path)?
+    } else {
+        Properties::default()
+    };
+
+    properties.set(key, value);
+    properties.write(&path)?;
+
+    if session::is_active(server)? {
+        eprintln!("Warning: {server} is running and must be restarted for the change to apply");
+    }
+
+    Ok(())
+}
+
+pub fn list(server: impl AsRef<str>, game_version: Option<&str>) -> Result<()> {
+    let properties = Properties::read(get_path(server.as_ref())?)?;
+
+    for (key, value) in properties.iter() {
+        println!("{key}={value}");
+
+        if let Err(err) = validate(key, value, game_version) {
+            eprintln!("  Warning: {err}");
+        }
+    }
+
+    Ok(())
+}
+
+pub fn diff(
+    server: impl AsRef<str>,
+    other: Option<impl AsRef<str>>,
+    game_version: Option<&str>,
+) -> Result<()> {
+    let properties = Properties::read(get_path(server.as_ref())?)?;
+    // unset keys fall back to their defaults, so they only matter when comparing servers
+    let show_missing = other.is_some();
+
+    let other: Vec<(String, String)> = match other {
+        Some(other) => Properties::read(get_path(other.as_ref())?)?
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        None => defaults(game_version)
+            .map(|schema| (schema.key.to_string(), schema.default.to_string()))
+            .collect(),
+    };
+
+    for (key, value) in &other {
+        match properties.get(key) {
+            Some(current) if current != value => println!("~ {key}: {value} -> {current}"),
+            Some(_) => {}
+            None if show_missing => println!("- {key}={value}"),
+            None => {}
+        }
+    }
+
+    for (key, value) in properties.iter() {
+        if !other.iter().any(|(k, _)| k == key) {
+            println!("+ {key}={value}");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,225 @@
+use crate::{
+    checksum::{Algorithm, Checksum},
+    config,
+    config_defs::{ProviderConfig, ProviderSource},
+    error::{Error, Result},
+    launch::Launch,
+    platforms::{self, Download, PlatformProvider},
+};
+use serde::Deserialize;
+use std::{path::Path, process::Command};
+use url::Url;
+
+// the index a json source is expected to return, newest versions and builds first
+#[derive(Debug, Deserialize)]
+struct JsonIndex {
+    versions: Vec<JsonVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonVersion {
+    version: String,
+    java_major: Option<u32>,
+    #[serde(default)]
+    builds: Vec<JsonBuild>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonBuild {
+    build: String,
+    url: String,
+    sha256: Option<String>,
+    sha1: Option<String>,
+    md5: Option<String>,
+}
+
+impl JsonBuild {
+    // the strongest published hash is verified
+    fn checksum(&self) -> Option<Checksum> {
+        [
+            (Algorithm::Sha256, &self.sha256),
+            (Algorithm::Sha1, &self.sha1),
+            (Algorithm::Md5, &self.md5),
+        ]
+        .into_iter()
+        .find_map(|(algorithm, hash)| hash.as_ref().map(|hash| Checksum::new(algorithm, hash)))
+    }
+}
+
+pub struct CustomProvider {
+    name: String,
+    config: ProviderConfig,
+}
+
+// provider names are matched case insensitively, like the built-in platforms
+pub fn get(name: &str) -> Result<CustomProvider> {
+    config::get()?
+        .providers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(key, config)| CustomProvider {
+            name: key.to_lowercase(),
+            config: config.clone(),
+        })
+        .ok_or_else(|| Error::UnknownPlatform(name.to_string()))
+}
+
+pub fn names() -> Result<Vec<String>> {
+    let mut names: Vec<_> = config::get()?.providers.keys().cloned().collect();
+    names.sort();
+    Ok(names)
+}
+
+// a checksum file holds the hash, optionally followed by the file name
+fn get_published_checksum(url: &str) -> Result<Checksum> {
+    let text = platforms::get_client()?
+        .get(url)
+        .send()?
+        .error_for_status()?
+        .text()?;
+    let hash = text.split_whitespace().next().unwrap_or_default();
+
+    let algorithm = match hash.len() {
+        32 => Algorithm::Md5,
+        40 => Algorithm::Sha1,
+        64 => Algorithm::Sha256,
+        _ => return Err(Error::UnknownChecksum(url.to_string())),
+    };
+
+    if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::UnknownChecksum(url.to_string()));
+    }
+
+    Ok(Checksum::new(algorithm, hash))
+}
+
+impl CustomProvider {
+    fn get_index(&self, url: &str) -> Result<JsonIndex> {
+        Ok(platforms::get_client()?
+            .get(url)
+            .send()?
+            .error_for_status()?
+            .json()?)
+    }
+
+    fn get_version(&self, url: &str, version: Option<String>) -> Result<JsonVersion> {
+        let versions = self.get_index(url)?.versions;
+
+        match version {
+            Some(version) => versions
+                .into_iter()
+                .find(|entry| entry.version == version)
+                .ok_or_else(|| {
+                    Error::PlatformsNotFound(format!("{} version {version}", self.name))
+                }),
+            None => versions
+                .into_iter()
+                .next()
+                .ok_or_else(|| Error::PlatformsNotFound(format!("{} version", self.name))),
+        }
+    }
+}
+
+impl PlatformProvider for CustomProvider {
+    fn versions(&self) -> Result<Vec<String>> {
+        match &self.config.source {
+            ProviderSource::Template { versions, .. } => Ok(versions.clone()),
+            ProviderSource::Json { url } => Ok(self
+                .get_index(url)?
+                .versions
+                .into_iter()
+                .map(|entry| entry.version)
+                .collect()),
+        }
+    }
+
+    fn builds(&self, version: &str) -> Result<Vec<String>> {
+        match &self.config.source {
+            ProviderSource::Template { .. } => Ok(vec![]),
+            ProviderSource::Json { url } => Ok(self
+                .get_version(url, Some(version.to_string()))?
+                .builds
+                .into_iter()
+                .map(|build| build.build)
+                .collect()),
+        }
+    }
+
+    fn download(&self, version: Option<String>) -> Result<Download> {
+        match &self.config.source {
+            // templates without a version list can only install the versions they are given
+            ProviderSource::Template {
+                url,
+                versions,
+                checksum_url,
+            } => {
+                let version = version
+                    .or_else(|| versions.first().cloned())
+                    .ok_or_else(|| Error::PlatformsNotFound(format!("{} version", self.name)))?;
+
+                Ok(Download {
+                    url: Url::parse(&url.replace("{version}", &version))?,
+                    checksum: checksum_url
+                        .as_ref()
+                        .map(|checksum_url| {
+                            get_published_checksum(&checksum_url.replace("{version}", &version))
+                        })
+                        .transpose()?,
+                    version,
+                    build: None,
+                    java_major: self.config.java_major,
+                })
+            }
+            ProviderSource::Json { url } => {
+                let entry = self.get_version(url, version)?;
+                let build = entry.builds.into_iter().next().ok_or_else(|| {
+                    Error::PlatformsNotFound(format!("{} build for {}", self.name, entry.version))
+                })?;
+
+                Ok(Download {
+                    url: Url::parse(&build.url)?,
+                    checksum: build.checksum(),
+                    version: entry.version,
+                    build: Some(build.build),
+                    java_major: entry.java_major.or(self.config.java_major),
+                })
+            }
+        }
+    }
+
+    fn post_install(&self, server_dir: &Path) -> Result<()> {
+        let Some(command) = &self.config.post_install else {
+            return Ok(());
+        };
+
+        println!("Running the {} post install command...", self.name);
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .current_dir(server_dir)
+            .status()?;
+
+        if !status.success() {
+            return Err(Error::CommandFailure {
+                code: status.code(),
+                stderr: None,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn launch(&self) -> Launch {
+        match &self.config.launch {
+            Some(template) => Launch::Command {
+                template: template.clone(),
+                memory: None,
+            },
+            None => Launch::Jar,
+        }
+    }
+
+    fn is_proxy(&self) -> bool {
+        self.config.proxy
+    }
+}
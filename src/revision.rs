@@ -0,0 +1,248 @@
+use crate::{
+    error::{Error, Result},
+    server::{self, JAR_FILE_TXT_NAME, JAR_INFO_FILE, JarInfo, METADATA_DIRECTORY},
+    session,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const REVISIONS_DIRECTORY: &str = "revisions";
+pub const STAGING_DIRECTORY: &str = "staging";
+const MAX_REVISIONS: usize = 5;
+
+fn get_revisions(metadata_dir: &Path) -> Result<Vec<PathBuf>> {
+    let revisions_dir = metadata_dir.join(REVISIONS_DIRECTORY);
+
+    if !revisions_dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut revisions = vec![];
+    for entry in fs::read_dir(revisions_dir)? {
+        let entry = entry?;
+        if let Ok(timestamp) = entry.file_name().to_string_lossy().parse::<u64>() {
+            revisions.push((timestamp, entry.path()));
+        }
+    }
+
+    revisions.sort();
+    Ok(revisions.into_iter().map(|(_, path)| path).collect())
+}
+
+fn read_jar_file_name(metadata_dir: &Path) -> Result<String> {
+    Ok(fs::read_to_string(metadata_dir.join(JAR_FILE_TXT_NAME))?
+        .trim_end()
+        .to_string())
+}
+
+fn link_or_copy(src: &Path, dst: &Path) -> Result<()> {
+    if fs::hard_link(src, dst).is_err() {
+        fs::copy(src, dst)?;
+    }
+    Ok(())
+}
+
+// prepares the jar and its metadata next to the live files so that they can be renamed into place
+fn stage(
+    metadata_dir: &Path,
+    jar_source: &Path,
+    jar_file_name: &str,
+    jar_info: Option<&JarInfo>,
+) -> Result<PathBuf> {
+    let staging_dir = metadata_dir.join(STAGING_DIRECTORY);
+
+    if staging_dir.exists() {
+        server::remove_dir_with_retries(&staging_dir)?;
+    }
+    fs::create_dir_all(&staging_dir)?;
+
+    fs::copy(jar_source, staging_dir.join(jar_file_name))?;
+
+    let jar_file_txt = server::set_jar_file_metadata(&staging_dir, jar_file_name)?;
+    let mut perms = jar_file_txt.metadata()?.permissions();
+    perms.set_readonly(true);
+    jar_file_txt.set_permissions(perms)?;
+
+    if let Some(jar_info) = jar_info {
+        server::set_jar_info(&staging_dir, jar_info)?;
+    }
+
+    Ok(staging_dir)
+}
+
+// each rename is atomic, so the server always has a complete jar to start from
+fn commit(server_dir: &Path, staging_dir: &Path, jar_file_name: &str) -> Result<()> {
+    let metadata_dir = server_dir.join(METADATA_DIRECTORY);
+    let previous_jar_file_name = read_jar_file_name(&metadata_dir).ok();
+
+    fs::rename(
+        staging_dir.join(jar_file_name),
+        server_dir.join(jar_file_name),
+    )?;
+    fs::rename(
+        staging_dir.join(JAR_FILE_TXT_NAME),
+        metadata_dir.join(JAR_FILE_TXT_NAME),
+    )?;
+
+    let staged_jar_info = staging_dir.join(JAR_INFO_FILE);
+    if staged_jar_info.is_file() {
+        fs::rename(staged_jar_info, metadata_dir.join(JAR_INFO_FILE))?;
+    } else if metadata_dir.join(JAR_INFO_FILE).is_file() {
+        fs::remove_file(metadata_dir.join(JAR_INFO_FILE))?;
+    }
+
+    fs::remove_dir_all(staging_dir)?;
+
+    if let Some(previous) = previous_jar_file_name
+        && previous != jar_file_name
+        && server_dir.join(&previous).is_file()
+    {
+        fs::remove_file(server_dir.join(previous))?;
+    }
+
+    Ok(())
+}
+
+fn record(server_dir: &Path) -> Result<PathBuf> {
+    let metadata_dir = server_dir.join(METADATA_DIRECTORY);
+    let revisions_dir = metadata_dir.join(REVISIONS_DIRECTORY);
+
+    let mut timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| Error::TimeWentBackwards)?
+        .as_secs();
+    while revisions_dir.join(timestamp.to_string()).exists() {
+        timestamp += 1;
+    }
+
+    let revision_dir = revisions_dir.join(timestamp.to_string());
+    fs::create_dir_all(&revision_dir)?;
+
+    let result = (|| {
+        let jar_path = server::get_server_jar_path(server_dir)?;
+        let jar_file_name = read_jar_file_name(&metadata_dir)?;
+
+        link_or_copy(&jar_path, &revision_dir.join(&jar_file_name))?;
+        fs::copy(
+            metadata_dir.join(JAR_FILE_TXT_NAME),
+            revision_dir.join(JAR_FILE_TXT_NAME),
+        )?;
+
+        if metadata_dir.join(JAR_INFO_FILE).is_file() {
+            fs::copy(
+                metadata_dir.join(JAR_INFO_FILE),
+                revision_dir.join(JAR_INFO_FILE),
+            )?;
+        }
+
+        Ok(())
+    })();
+
+    if let Err(err) = result {
+        server::remove_dir_with_retries(&revision_dir)?;
+        return Err(err);
+    }
+
+    Ok(revision_dir)
+}
+
+fn prune(metadata_dir: &Path) -> Result<()> {
+    let revisions = get_revisions(metadata_dir)?;
+
+    for revision in revisions
+        .iter()
+        .take(revisions.len().saturating_sub(MAX_REVISIONS))
+    {
+        server::remove_dir_with_retries(revision)?;
+    }
+
+    Ok(())
+}
+
+// installers rewrite the libraries and launch file in place, so none of the recorded jars can be
+// rolled back to on their own anymore
+pub fn clear(server_dir: &Path) -> Result<()> {
+    let revisions_dir = server_dir
+        .join(METADATA_DIRECTORY)
+        .join(REVISIONS_DIRECTORY);
+
+    if revisions_dir.is_dir() {
+        server::remove_dir_with_retries(&revisions_dir)?;
+    }
+
+    Ok(())
+}
+
+// switches the server to a new jar, keeping the current one as a revision for `rollback`
+pub fn switch(
+    server_dir: &Path,
+    jar_source: &Path,
+    jar_file_name: &str,
+    jar_info: &JarInfo,
+) -> Result<()> {
+    let metadata_dir = server_dir.join(METADATA_DIRECTORY);
+    let staging_dir = stage(&metadata_dir, jar_source, jar_file_name, Some(jar_info))?;
+
+    let revision_dir = if server::get_server_jar_path(server_dir).is_ok() {
+        match record(server_dir) {
+            Ok(revision_dir) => Some(revision_dir),
+            Err(err) => {
+                server::remove_dir_with_retries(&staging_dir)?;
+                return Err(err);
+            }
+        }
+    } else {
+        None
+    };
+
+    commit(server_dir, &staging_dir, jar_file_name)?;
+    prune(&metadata_dir)?;
+
+    match revision_dir.as_deref().and_then(Path::file_name) {
+        Some(revision) => println!(
+            "Switched to {jar_file_name}, the previous jar was kept as revision {}",
+            revision.to_string_lossy()
+        ),
+        None => println!("Switched to {jar_file_name}"),
+    }
+
+    Ok(())
+}
+
+pub fn rollback(server: impl AsRef<str>) -> Result<()> {
+    let server = server.as_ref();
+    let server_dir = server::get_server_dir_required(server)?;
+    let metadata_dir = server_dir.join(METADATA_DIRECTORY);
+
+    let revision_dir = get_revisions(&metadata_dir)?
+        .pop()
+        .ok_or_else(|| Error::NoRevisions(server.to_string()))?;
+
+    let jar_file_name = read_jar_file_name(&revision_dir)?;
+    let jar_info = server::read_jar_info(&revision_dir)?;
+
+    let staging_dir = stage(
+        &metadata_dir,
+        &revision_dir.join(&jar_file_name),
+        &jar_file_name,
+        jar_info.as_ref(),
+    )?;
+    commit(&server_dir, &staging_dir, &jar_file_name)?;
+    server::remove_dir_with_retries(&revision_dir)?;
+
+    println!(
+        "Rolled {server} back to {jar_file_name}{}",
+        jar_info
+            .and_then(|jar_info| jar_info.version)
+            .map_or(String::new(), |version| format!(" (version {version})"))
+    );
+
+    if session::is_active(server)? {
+        println!("Restart {server} to run the previous jar");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,395 @@
+use crate::{
+    config_defs::Password,
+    error::{Error, Result},
+};
+use hmac::{Hmac, KeyInit, Mac};
+use reqwest::{
+    Method,
+    blocking::{Body, Client, RequestBuilder, Response},
+};
+use sha2::{Digest, Sha256};
+use std::{
+    env,
+    fs::File,
+    io,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use url::Url;
+
+const DEFAULT_REGION: &str = "us-east-1";
+const SERVICE: &str = "s3";
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+pub struct Bucket<'a> {
+    client: Client,
+    endpoint: Url,
+    name: &'a str,
+    region: &'a str,
+    access_key: String,
+    secret_key: String,
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut encoded = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            b'/' if !encode_slash => encoded.push('/'),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+
+    encoded
+}
+
+// returns the YYYYMMDD date and the YYYYMMDDTHHMMSSZ timestamp
+fn format_timestamp(secs: u64) -> (String, String) {
+    // days to civil date conversion, see http://howardhinnant.github.io/date_algorithms.html
+    let days = (secs / 86400) as i64 + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    let seconds = secs % 86400;
+    let date = format!("{year:04}{month:02}{day:02}");
+    let timestamp = format!(
+        "{date}T{:02}{:02}{:02}Z",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    );
+
+    (date, timestamp)
+}
+
+fn canonical_query(query: &[(&str, &str)]) -> String {
+    let mut query: Vec<_> = query
+        .iter()
+        .map(|(k, v)| (uri_encode(k, true), uri_encode(v, true)))
+        .collect();
+    query.sort();
+
+    query
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+// the headers have to be lowercase and sorted by name
+fn signed_headers(headers: &[(&str, &str)]) -> String {
+    headers
+        .iter()
+        .map(|(name, _)| *name)
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn canonical_request(
+    method: &str,
+    uri: &str,
+    query: &str,
+    headers: &[(&str, &str)],
+    payload_hash: &str,
+) -> String {
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{name}:{value}\n"))
+        .collect();
+
+    format!(
+        "{method}\n{uri}\n{query}\n{canonical_headers}\n{}\n{payload_hash}",
+        signed_headers(headers)
+    )
+}
+
+fn string_to_sign(timestamp: &str, scope: &str, canonical_request: &str) -> String {
+    format!(
+        "AWS4-HMAC-SHA256\n{timestamp}\n{scope}\n{}",
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    )
+}
+
+fn signing_key(secret_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    [region, service, "aws4_request"].iter().fold(
+        hmac(format!("AWS4{secret_key}").as_bytes(), date),
+        |key, part| hmac(&key, part),
+    )
+}
+
+fn xml_values<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+
+    xml.split(&open)
+        .skip(1)
+        .filter_map(|slice| slice.split(&close).next())
+        .collect()
+}
+
+fn check_status(response: Response) -> Result<Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+
+    let status = response.status().as_u16();
+    let body = response.text().unwrap_or_default();
+
+    Err(Error::S3Request {
+        status,
+        message: xml_values(&body, "Message")
+            .first()
+            .map_or(body.clone(), |message| message.to_string()),
+    })
+}
+
+impl<'a> Bucket<'a> {
+    pub fn new(
+        endpoint: &str,
+        name: &'a str,
+        region: Option<&'a str>,
+        access_key: Option<&str>,
+        secret_key: Option<&Password>,
+    ) -> Result<Self> {
+        let access_key = access_key
+            .map(String::from)
+            .or_else(|| env::var("AWS_ACCESS_KEY_ID").ok())
+            .ok_or_else(|| Error::MissingCredentials("S3 access key".to_string()))?;
+        let secret_key = secret_key
+            .map(|password| password.0.clone())
+            .or_else(|| env::var("AWS_SECRET_ACCESS_KEY").ok())
+            .ok_or_else(|| Error::MissingCredentials("S3 secret key".to_string()))?;
+
+        Ok(Self {
+            client: Client::new(),
+            endpoint: Url::parse(endpoint)?,
+            name,
+            region: region.unwrap_or(DEFAULT_REGION),
+            access_key,
+            secret_key,
+        })
+    }
+
+    fn request(&self, method: Method, key: &str, query: &[(&str, &str)]) -> Result<RequestBuilder> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| Error::TimeWentBackwards)?
+            .as_secs();
+        let (date, timestamp) = format_timestamp(now);
+
+        let base_path = self.endpoint.path().trim_end_matches('/');
+        let path = if key.is_empty() {
+            format!("{base_path}/{}", self.name)
+        } else {
+            format!("{base_path}/{}/{key}", self.name)
+        };
+        let canonical_uri = uri_encode(&path, false);
+
+        let canonical_query = canonical_query(query);
+
+        let host = match self.endpoint.port() {
+            Some(port) => format!("{}:{port}", self.endpoint.host_str().unwrap_or_default()),
+            None => self.endpoint.host_str().unwrap_or_default().to_string(),
+        };
+
+        let headers = [
+            ("host", host.as_str()),
+            ("x-amz-content-sha256", UNSIGNED_PAYLOAD),
+            ("x-amz-date", timestamp.as_str()),
+        ];
+        let canonical_request = canonical_request(
+            method.as_str(),
+            &canonical_uri,
+            &canonical_query,
+            &headers,
+            UNSIGNED_PAYLOAD,
+        );
+
+        let scope = format!("{date}/{}/{SERVICE}/aws4_request", self.region);
+        let signing_key = signing_key(&self.secret_key, &date, self.region, SERVICE);
+        let signature = hex::encode(hmac(
+            &signing_key,
+            &string_to_sign(&timestamp, &scope, &canonical_request),
+        ));
+
+        let mut url = self.endpoint.clone();
+        url.set_path(&canonical_uri);
+        url.set_query((!canonical_query.is_empty()).then_some(canonical_query.as_str()));
+
+        Ok(self
+            .client
+            .request(method, url)
+            .header("x-amz-content-sha256", UNSIGNED_PAYLOAD)
+            .header("x-amz-date", &timestamp)
+            .header(
+                "authorization",
+                format!(
+                    "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={}, Signature={signature}",
+                    self.access_key,
+                    signed_headers(&headers)
+                ),
+            ))
+    }
+
+    pub fn put_file(&self, key: &str, path: impl AsRef<Path>) -> Result<()> {
+        let file = File::open(path)?;
+        let length = file.metadata()?.len();
+
+        check_status(
+            self.request(Method::PUT, key, &[])?
+                .body(Body::sized(file, length))
+                .send()?,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn get_file(&self, key: &str, path: impl AsRef<Path>) -> Result<()> {
+        let mut response = check_status(self.request(Method::GET, key, &[])?.send()?)?;
+        io::copy(&mut response, &mut File::create(path)?)?;
+        Ok(())
+    }
+
+    pub fn delete(&self, key: &str) -> Result<()> {
+        check_status(self.request(Method::DELETE, key, &[])?.send()?)?;
+        Ok(())
+    }
+
+    pub fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let mut keys = vec![];
+        let mut continuation_token = None;
+
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", prefix)];
+            if let Some(token) = continuation_token.as_deref() {
+                query.push(("continuation-token", token));
+            }
+
+            let body = check_status(self.request(Method::GET, "", &query)?.send()?)?.text()?;
+            keys.extend(xml_values(&body, "Key").into_iter().map(String::from));
+
+            if xml_values(&body, "IsTruncated").first() != Some(&"true") {
+                break;
+            }
+
+            continuation_token = xml_values(&body, "NextContinuationToken")
+                .first()
+                .map(|token| token.to_string());
+
+            if continuation_token.is_none() {
+                break;
+            }
+        }
+
+        Ok(keys)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // credentials and requests from the aws signature version 4 test suite
+    const SECRET_KEY: &str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+    const EMPTY_PAYLOAD_HASH: &str =
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    const HEADERS: [(&str, &str); 2] = [
+        ("host", "example.amazonaws.com"),
+        ("x-amz-date", "20150830T123600Z"),
+    ];
+    const SCOPE: &str = "20150830/us-east-1/service/aws4_request";
+
+    fn sign(canonical_request: &str) -> String {
+        let signing_key = signing_key(SECRET_KEY, "20150830", "us-east-1", "service");
+        hex::encode(hmac(
+            &signing_key,
+            &string_to_sign("20150830T123600Z", SCOPE, canonical_request),
+        ))
+    }
+
+    #[test]
+    fn get_vanilla() {
+        let request = canonical_request("GET", "/", "", &HEADERS, EMPTY_PAYLOAD_HASH);
+
+        assert_eq!(
+            request,
+            format!(
+                "GET\n/\n\nhost:example.amazonaws.com\nx-amz-date:20150830T123600Z\n\nhost;x-amz-date\n{EMPTY_PAYLOAD_HASH}"
+            )
+        );
+        assert_eq!(
+            string_to_sign("20150830T123600Z", SCOPE, &request),
+            format!(
+                "AWS4-HMAC-SHA256\n20150830T123600Z\n{SCOPE}\nbb579772317eb040ac9ed261061d46c1f17a8133879d6129b6e1c25292927e63"
+            )
+        );
+        assert_eq!(
+            sign(&request),
+            "5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn get_vanilla_query_order_key_case() {
+        let query = canonical_query(&[("Param2", "value2"), ("Param1", "value1")]);
+        assert_eq!(query, "Param1=value1&Param2=value2");
+
+        let request = canonical_request("GET", "/", &query, &HEADERS, EMPTY_PAYLOAD_HASH);
+        assert_eq!(
+            sign(&request),
+            "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500"
+        );
+    }
+
+    #[test]
+    fn query_values_are_encoded() {
+        assert_eq!(
+            canonical_query(&[("prefix", "backups/s 1"), ("list-type", "2")]),
+            "list-type=2&prefix=backups%2Fs%201"
+        );
+    }
+
+    #[test]
+    fn derived_signing_key() {
+        assert_eq!(
+            hex::encode(signing_key(SECRET_KEY, "20120215", "us-east-1", "iam")),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+    }
+
+    #[test]
+    fn civil_dates() {
+        for (secs, date, timestamp) in [
+            (0, "19700101", "19700101T000000Z"),
+            (946684799, "19991231", "19991231T235959Z"),
+            (951868799, "20000229", "20000229T235959Z"),
+            (1440938160, "20150830", "20150830T123600Z"),
+            (4107542400, "21000301", "21000301T000000Z"),
+        ] {
+            assert_eq!(
+                format_timestamp(secs),
+                (date.to_string(), timestamp.to_string())
+            );
+        }
+    }
+}
@@ -1,33 +1,52 @@
 use crate::{
+    backup, cache,
+    checksum::Checksum,
     config::{self, get_expanded_servers_dir, server_or_current},
+    config_defs::{Password, RconConfig},
     error::{Error, Result},
+    exclude::{self, Excludes},
+    installer, launch,
     platforms::{self, Platform},
-    session,
-};
-use reqwest::{
-    blocking::{self, Response},
-    header,
+    ports,
+    properties::{self, Properties},
+    revision, session,
 };
+use rand::{Rng, distr::Alphanumeric};
+use serde::{Deserialize, Serialize};
 use std::{
     collections::HashSet,
     env,
     ffi::OsStr,
     fmt::{self, Display, Formatter},
     fs::{self, File},
-    io::{self, Write},
+    io::{self, BufRead, BufReader, IsTerminal, Write},
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, Stdio},
     time::{SystemTime, UNIX_EPOCH},
 };
-use url::Url;
 
 const REPO_URL: &str = env!("CARGO_PKG_REPOSITORY");
-const TEMPLATE_SUFFIX: &str = ".template";
+pub const TEMPLATE_SUFFIX: &str = ".template";
 
-const METADATA_DIRECTORY: &str = ".mcserver";
-const JAR_FILE_TXT_NAME: &str = "jar_file.txt";
+pub const METADATA_DIRECTORY: &str = ".mcserver";
+pub const JAR_FILE_TXT_NAME: &str = "jar_file.txt";
+pub const JAR_INFO_FILE: &str = "jar.toml";
 const LAST_USED_FILE: &str = "last_used.timestamp";
 
+const RCON_PASSWORD_LENGTH: usize = 24;
+
+const EULA_FILE: &str = "eula.txt";
+const EULA_URL: &str = "https://aka.ms/MinecraftEULA";
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct JarInfo {
+    pub platform: Option<Platform>,
+    pub version: Option<String>,
+    pub build: Option<String>,
+    pub checksum: Option<Checksum>,
+    pub java_major: Option<u32>,
+}
+
 pub struct ServerObject {
     pub name: String,
     pub tags: Vec<String>,
@@ -81,8 +100,32 @@ pub fn remove_dir_with_retries(dir: impl AsRef<Path>) -> Result<()> {
     unreachable!("Code returns before the for loop ends")
 }
 
+pub fn move_directory(src: &Path, dst: &Path) -> Result<()> {
+    match fs::rename(src, dst) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            copy_directory(src, dst)?;
+            remove_dir_with_retries(src)
+        }
+        Err(err) => Err(Error::Io(err)),
+    }
+}
+
 fn remove_server(server: String) -> Result<()> {
-    remove_dir_with_retries(get_expanded_servers_dir()?.join(server))?;
+    remove_dir_with_retries(get_expanded_servers_dir()?.join(&server))?;
+
+    // a network can't run without its proxy, while the other backends keep working
+    let mut config = config::get()?;
+    config.networks.retain(|name, network| {
+        if network.proxy == server {
+            println!("Removed network {name}, whose proxy was {server}");
+        }
+        network.proxy != server
+    });
+    for network in config.networks.values_mut() {
+        network.backends.retain(|backend| *backend != server);
+    }
+
     Ok(())
 }
 
@@ -145,93 +188,304 @@ where
     M: AsRef<Path>,
     J: Display,
 {
-    let mut jar_file_txt = File::create(metadata_dir.as_ref().join(JAR_FILE_TXT_NAME))?;
+    let path = metadata_dir.as_ref().join(JAR_FILE_TXT_NAME);
+
+    // the file is made read only, so it has to be replaced rather than overwritten
+    if path.exists() {
+        fs::remove_file(&path)?;
+    }
+
+    let mut jar_file_txt = File::create(path)?;
     writeln!(jar_file_txt, "{jar_file_name}")?;
     Ok(jar_file_txt)
 }
 
-pub fn set_default_metadata<M, J>(metadata_dir: M, jar_file_name: J) -> Result<()>
+pub fn set_jar_info(metadata_dir: impl AsRef<Path>, jar_info: &JarInfo) -> Result<()> {
+    fs::write(
+        metadata_dir.as_ref().join(JAR_INFO_FILE),
+        toml::to_string(jar_info)?,
+    )?;
+    Ok(())
+}
+
+pub fn read_jar_info(metadata_dir: impl AsRef<Path>) -> Result<Option<JarInfo>> {
+    let path = metadata_dir.as_ref().join(JAR_INFO_FILE);
+
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    Ok(Some(toml::from_str(&fs::read_to_string(path)?)?))
+}
+
+pub fn get_jar_info(server_dir: impl AsRef<Path>) -> Result<Option<JarInfo>> {
+    read_jar_info(server_dir.as_ref().join(METADATA_DIRECTORY))
+}
+
+fn set_jar_file_name<M, J>(metadata_dir: M, jar_file_name: Option<J>) -> Result<()>
 where
     M: AsRef<Path>,
     J: Display,
 {
-    fs::create_dir_all(&metadata_dir)?;
+    match jar_file_name {
+        Some(jar_file_name) => {
+            let jar_file_txt = set_jar_file_metadata(&metadata_dir, jar_file_name)?;
 
-    let jar_file_txt = set_jar_file_metadata(&metadata_dir, jar_file_name)?;
+            let mut perms = jar_file_txt.metadata()?.permissions();
+            perms.set_readonly(true);
+            jar_file_txt.set_permissions(perms)?;
+        }
+        // servers started through an args file may not have a jar at all
+        None => {
+            let path = metadata_dir.as_ref().join(JAR_FILE_TXT_NAME);
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        }
+    }
 
-    let mut perms = jar_file_txt.metadata()?.permissions();
-    perms.set_readonly(true);
-    jar_file_txt.set_permissions(perms)?;
+    Ok(())
+}
 
+pub fn set_default_metadata<M, J>(metadata_dir: M, jar_file_name: Option<J>) -> Result<()>
+where
+    M: AsRef<Path>,
+    J: Display,
+{
+    fs::create_dir_all(&metadata_dir)?;
+    set_jar_file_name(&metadata_dir, jar_file_name)?;
     set_last_used_metadata(&metadata_dir, u64::MAX)?;
 
     Ok(())
 }
 
-fn copy_jar<S, J, F>(server_dir: S, mut jar: J, file_name: F) -> Result<()>
-where
-    S: AsRef<Path>,
-    J: io::Read,
-    F: AsRef<Path>,
-{
-    env::set_current_dir(server_dir)?;
+// declines when there is nobody to ask, so that scripts don't hang on the prompt
+fn confirm_eula() -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(false);
+    }
 
-    let mut jar_file = File::create(file_name)?;
-    io::copy(&mut jar, &mut jar_file)?;
+    loop {
+        print!("Do you accept the Minecraft EULA ({EULA_URL})? (y/n): ");
+        io::stdout().flush()?;
+
+        let mut confirmation = String::new();
+        if io::stdin().read_line(&mut confirmation)? == 0 {
+            println!();
+            break Ok(false);
+        }
+
+        match confirmation.trim_end().to_lowercase().as_str() {
+            "y" | "yes" => break Ok(true),
+            "n" | "no" => break Ok(false),
+            _ => {}
+        }
+    }
+}
+
+pub fn accept_eula(server_dir: impl AsRef<Path>) -> Result<()> {
+    fs::write(
+        server_dir.as_ref().join(EULA_FILE),
+        format!(
+            "#By changing the setting below to TRUE you are indicating your agreement to our EULA ({EULA_URL}).\neula=true\n"
+        ),
+    )?;
 
     Ok(())
 }
 
-pub fn get_jar(download_url: Url, platform: Platform) -> Result<(Response, String)> {
-    println!("Downloading from {download_url}...");
-    let response = blocking::get(download_url)?;
+pub fn run_headless(server_dir: impl AsRef<Path>) -> Result<()> {
+    let server_dir = server_dir.as_ref();
+    let command_line = launch::read(server_dir)?.command_line(server_dir, true)?;
+    let is_proxy = get_jar_info(server_dir)?
+        .and_then(|jar_info| jar_info.platform)
+        .is_some_and(|platform| platforms::is_proxy(&platform));
+
+    println!("Running server to generate default files...");
+
+    // exec keeps the server as the child so that stop reaches it
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(format!("exec {command_line}"))
+        .current_dir(server_dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().ok_or(io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "Failed to take stdin pipe",
+    ))?;
+
+    let stdout = child.stdout.take().ok_or(io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "Failed to take stdout pipe",
+    ))?;
+
+    let mut stopping = false;
+
+    // keep reading until the server exits so that it never blocks on a full pipe
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+
+        // bungeecord doesn't report when it's done, only when its listeners are up
+        if !stopping && (line.contains("Done (") || line.contains("Listening on ")) {
+            writeln!(stdin, "{}", if is_proxy { "end" } else { "stop" })?;
+            stopping = true;
+        }
+    }
 
-    let file_name = response
-        .headers()
-        .get(header::CONTENT_DISPOSITION)
-        .map(|disposition| disposition.to_str())
-        .transpose()?
-        .and_then(|cd| cd.split("filename=\"").nth(1))
-        .and_then(|slice| slice.split('"').next())
-        .map(String::from)
-        .unwrap_or_else(|| format!("{platform}.jar"));
+    let status = child.wait()?;
 
-    // if let Err(err) = copy_jar(&server_dir, file_name, response) {
-    //     remove_dir_with_retries(server_dir)?;
-    //     return Err(err);
-    // }
+    if !status.success() {
+        return Err(Error::CommandFailure {
+            code: status.code(),
+            stderr: None,
+        });
+    }
 
-    Ok((response, file_name))
+    Ok(())
 }
 
-pub fn create_new<N>(platform: Platform, version: Option<String>, name: Option<N>) -> Result<()>
+pub fn create_new<N>(
+    platform: Platform,
+    version: Option<String>,
+    name: Option<N>,
+    eula_accepted: bool,
+    init: bool,
+) -> Result<()>
 where
     N: Display,
 {
-    let download_url = platforms::get(platform, version)?;
+    let provider = platforms::get_provider(&platform)?;
+    let download = provider.resolve(version)?;
 
     let server_dir = match name {
         Some(name) => get_first_server_path(name)?,
         None => get_first_server_path(format!("{platform}-server"))?,
     };
 
+    let jar = cache::get_jar(&platform, &download)?;
+
     fs::create_dir_all(&server_dir)?;
-    let (jar, jar_file_name) = get_jar(download_url, platform)?;
-    copy_jar(&server_dir, jar, &jar_file_name)?;
-    set_default_metadata(server_dir.join(METADATA_DIRECTORY), jar_file_name)?;
+
+    // a half built server would block retrying with the same name
+    let result = (|| {
+        let installed = match provider.install(&download, &jar, &server_dir)? {
+            Some(installed) => installed,
+            None => {
+                fs::copy(&jar.path, server_dir.join(&jar.file_name))?;
+                installer::Installed {
+                    jar_file_name: Some(jar.file_name),
+                    launch: provider.launch(),
+                }
+            }
+        };
+
+        let jar_info = JarInfo {
+            platform: Some(platform),
+            version: Some(download.version),
+            build: download.build,
+            checksum: Some(jar.checksum),
+            java_major: download.java_major,
+        };
+        set_default_metadata(server_dir.join(METADATA_DIRECTORY), installed.jar_file_name)?;
+        set_jar_info(server_dir.join(METADATA_DIRECTORY), &jar_info)?;
+        launch::write(&server_dir, &installed.launch)?;
+        provider.post_install(&server_dir)?;
+
+        // proxies bind the port from their own config and have no eula
+        let mut can_start = true;
+        if !provider.is_proxy() {
+            ports::allocate(&get_dir_name(&server_dir), &server_dir)?;
+
+            if eula_accepted || confirm_eula()? {
+                accept_eula(&server_dir)?;
+            } else {
+                println!(
+                    "The EULA must be accepted in {EULA_FILE} before the server can be deployed"
+                );
+                can_start = false;
+            }
+        }
+
+        Ok(can_start)
+    })();
+
+    let can_start = match result {
+        Ok(can_start) => can_start,
+        Err(err) => {
+            remove_dir_with_retries(&server_dir)?;
+            return Err(err);
+        }
+    };
+
+    // without the eula the server exits before generating anything
+    if init && !can_start {
+        println!("Skipping --init, the server can't start until the EULA is accepted");
+    } else if init {
+        run_headless(&server_dir)?;
+    }
+
     Ok(())
 }
 
-pub fn update_existing<S>(server: S, platform: Platform, version: Option<String>) -> Result<()>
-where
-    S: AsRef<Path>,
-{
-    let download_url = platforms::get(platform, version)?;
-    let server_dir = get_expanded_servers_dir()?.join(&server);
+pub fn update_existing(
+    server: impl AsRef<str>,
+    platform: Platform,
+    version: Option<String>,
+    take_backup: bool,
+) -> Result<()> {
+    let server = server.as_ref();
+    let server_dir = get_server_dir_required(server)?;
+
+    let provider = platforms::get_provider(&platform)?;
+    let download = provider.resolve(version)?;
+    let jar = cache::get_jar(&platform, &download)?;
+
+    if take_backup {
+        backup::create(server)?;
+    }
+
+    // installers update the server in place, so there's no revision to roll back to
+    if let Some(installed) = provider.install(&download, &jar, &server_dir)? {
+        let metadata_dir = server_dir.join(METADATA_DIRECTORY);
+
+        set_jar_file_name(&metadata_dir, installed.jar_file_name.as_ref())?;
+        revision::clear(&server_dir)?;
+        set_jar_info(
+            &metadata_dir,
+            &JarInfo {
+                platform: Some(platform),
+                version: Some(download.version),
+                build: download.build,
+                checksum: Some(jar.checksum),
+                java_major: download.java_major,
+            },
+        )?;
+        launch::write(&server_dir, &installed.launch)?;
+
+        match &installed.jar_file_name {
+            Some(jar_file_name) => println!("Installed {jar_file_name}"),
+            None => println!("Installed {}", installed.launch),
+        }
+    } else {
+        let jar_info = JarInfo {
+            platform: Some(platform),
+            version: Some(download.version),
+            build: download.build,
+            checksum: Some(jar.checksum),
+            java_major: download.java_major,
+        };
+        revision::switch(&server_dir, &jar.path, &jar.file_name, &jar_info)?;
+        launch::write(&server_dir, &provider.launch())?;
+    }
+    provider.post_install(&server_dir)?;
 
-    let (jar, jar_file_name) = get_jar(download_url, platform)?;
-    copy_jar(&server, jar, &jar_file_name)?;
-    set_jar_file_metadata(server_dir.join(METADATA_DIRECTORY), jar_file_name)?;
+    if session::is_active(server)? {
+        println!("Restart {server} to run the new jar");
+    }
 
     Ok(())
 }
@@ -337,6 +591,13 @@ pub fn for_each(mut f: impl FnMut(String)) -> Result<()> {
     for entry in fs::read_dir(servers_dir)? {
         let entry = entry?;
         let file_name = entry.file_name().to_string_lossy().to_string();
+
+        // hidden directories are used for staging restores and imports, and legacy templates
+        // live next to servers without being servers themselves
+        if file_name.starts_with('.') || is_template(&file_name) {
+            continue;
+        }
+
         f(file_name);
     }
 
@@ -361,11 +622,9 @@ pub fn get_server_dir_required(server: impl AsRef<Path>) -> Result<PathBuf> {
     Ok(server_dir)
 }
 
-fn get_server_jar_path(server_dir: impl AsRef<Path>) -> Result<PathBuf> {
+pub fn get_server_jar_path(server_dir: impl AsRef<Path>) -> Result<PathBuf> {
     let server_dir = server_dir.as_ref();
-    let jar_file_txt = server_dir
-        .join(METADATA_DIRECTORY)
-        .join(JAR_FILE_TXT_NAME);
+    let jar_file_txt = server_dir.join(METADATA_DIRECTORY).join(JAR_FILE_TXT_NAME);
 
     if !jar_file_txt.is_file() {
         return Err(Error::MissingFile { file: jar_file_txt });
@@ -389,14 +648,13 @@ pub fn get_command(server: impl AsRef<str>) -> Result<String> {
     }
 
     let server_dir = get_server_dir_required(server)?;
-    let config = &config::get()?;
+    let nogui = config::get()?.nogui;
+    let command_line = launch::read(&server_dir)?.command_line(&server_dir, nogui)?;
     Ok(format!(
-        "{} action rename-tab Server && cd {} && java -jar {} {} {} && {} kill-session $ZELLIJ_SESSION_NAME",
+        "{} action rename-tab Server && cd {} && {} && {} kill-session $ZELLIJ_SESSION_NAME",
         session::BASE_COMMAND,
         server_dir.to_string_lossy(),
-        config.default_java_args,
-        get_server_jar_path(&server_dir)?.to_string_lossy(),
-        if config.nogui { "nogui" } else { "" },
+        command_line,
         session::BASE_COMMAND
     ))
 }
@@ -419,31 +677,14 @@ pub fn is_template(server: impl AsRef<str>) -> bool {
     server.as_ref().ends_with(TEMPLATE_SUFFIX)
 }
 
-pub fn new_template(server: impl AsRef<str>) -> Result<()> {
-    let server = server.as_ref();
-    if is_template(server) {
-        return Err(Error::TemplateUsedForTemplate);
-    }
-    println!("Creating template using server {server}...");
-
-    let servers_dir = get_expanded_servers_dir()?;
-
-    let server_path = servers_dir.join(server);
-    if !server_path.exists() {
-        return Err(Error::ServerNotFound(server.to_string()));
-    }
-
-    let template_path = servers_dir.join(format!("{server}{TEMPLATE_SUFFIX}"));
-    if template_path.exists() {
-        return Err(Error::TemplateAlreadyExists(server.to_string()));
-    }
-
-    copy_directory(server_path, template_path)?;
-
-    Ok(())
+pub fn get_dir_name(path: impl AsRef<Path>) -> String {
+    path.as_ref()
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
 }
 
-fn get_first_server_path(name: impl Display) -> Result<PathBuf> {
+pub fn get_first_server_path(name: impl Display) -> Result<PathBuf> {
     let servers_dir = get_expanded_servers_dir()?;
     let path = servers_dir.join(format!("{name}"));
 
@@ -463,36 +704,138 @@ fn get_first_server_path(name: impl Display) -> Result<PathBuf> {
     })
 }
 
-pub fn from_template(template: impl AsRef<str>, server: Option<impl AsRef<str>>) -> Result<()> {
-    let template = template.as_ref();
+// names become directories next to the other servers, and a template suffix would hide them
+pub(crate) fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.contains(['/', '\\'])
+        || is_template(name)
+    {
+        return Err(Error::InvalidServerName(name.to_string()));
+    }
+
+    Ok(())
+}
+
+pub fn rename(old: impl AsRef<str>, new: impl AsRef<str>) -> Result<()> {
+    let (old, new) = (old.as_ref(), new.as_ref());
+    validate_name(new)?;
     let servers_dir = get_expanded_servers_dir()?;
 
-    let template_path = if template.ends_with(TEMPLATE_SUFFIX) {
-        println!("Creating server from {template}");
-        servers_dir.join(template)
-    } else {
-        let template_name = format!("{}{TEMPLATE_SUFFIX}", template);
-        println!("Creating server from {template_name}");
-        servers_dir.join(template_name)
-    };
+    let old_path = servers_dir.join(old);
+    if is_template(old) || !old_path.is_dir() {
+        return Err(Error::ServerNotFound(old.to_string()));
+    }
 
-    if !template_path.exists() {
-        return Err(Error::TemplateNotFound(template.to_string()));
+    let new_path = servers_dir.join(new);
+    if new_path.exists() {
+        return Err(Error::ServerAlreadyExists(new.to_string()));
     }
 
-    let server_path = match server {
-        Some(server) => {
-            let server = server.as_ref();
-            let path = get_expanded_servers_dir()?.join(server);
-            if path.exists() {
-                return Err(Error::ServerAlreadyExists(server.to_string()));
+    if session::is_active(old)? {
+        return Err(Error::ServerActive(old.to_string()));
+    }
+
+    fs::rename(old_path, new_path)?;
+
+    {
+        let mut config = config::get()?;
+
+        if let Some(rcon) = config.rcon.remove(old) {
+            config.rcon.insert(new.to_string(), rcon);
+        }
+
+        if config.default_server == old {
+            config.default_server = new.to_string();
+        }
+
+        for network in config.networks.values_mut() {
+            if network.proxy == old {
+                network.proxy = new.to_string();
+            }
+            for backend in network
+                .backends
+                .iter_mut()
+                .filter(|backend| *backend == old)
+            {
+                *backend = new.to_string();
             }
-            path
         }
-        None => get_first_server_path(template)?,
+    }
+
+    if session::is_dead(old)? {
+        session::delete_server_session(old, false)?;
+    }
+
+    println!("Renamed {old} to {new}");
+    Ok(())
+}
+
+fn generate_password() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(RCON_PASSWORD_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+// copies get their own rcon password, so that they can't be controlled with the original's
+pub fn reset_rcon_password(server: &str, server_dir: &Path, src: &str) -> Result<()> {
+    let password = generate_password();
+    let properties_path = server_dir.join(properties::FILE_NAME);
+
+    let mut server_properties = if properties_path.is_file() {
+        Properties::read(&properties_path)?
+    } else {
+        Properties::default()
     };
 
-    copy_directory(template_path, server_path)?;
+    let rcon_enabled = server_properties.get("enable-rcon") == Some("true");
+    server_properties.set("rcon.password", &password);
+    server_properties.write(&properties_path)?;
+
+    let mut config = config::get()?;
+    let src_rcon = config.rcon.get(src).cloned();
+
+    if rcon_enabled || src_rcon.is_some() {
+        config.rcon.insert(
+            server.to_string(),
+            RconConfig {
+                server_address: src_rcon
+                    .and_then(|rcon| rcon.server_address)
+                    .or_else(|| Some("localhost".to_string())),
+                port: None,
+                password: Some(Password(password)),
+            },
+        );
+    }
+
+    Ok(())
+}
+
+pub fn clone(src: impl AsRef<str>, dst: impl AsRef<str>, excludes: &[String]) -> Result<()> {
+    let (src, dst) = (src.as_ref(), dst.as_ref());
+    validate_name(dst)?;
+    let servers_dir = get_expanded_servers_dir()?;
+
+    let src_path = servers_dir.join(src);
+    if is_template(src) || !src_path.is_dir() {
+        return Err(Error::ServerNotFound(src.to_string()));
+    }
+
+    let dst_path = servers_dir.join(dst);
+    if dst_path.exists() {
+        return Err(Error::ServerAlreadyExists(dst.to_string()));
+    }
+
+    println!("Cloning {src} to {dst}...");
+    let excludes = Excludes::new(&src_path, excludes)?;
+    exclude::copy_directory(&src_path, &dst_path, &excludes)?;
+    set_last_used_metadata(dst_path.join(METADATA_DIRECTORY), u64::MAX)?;
+
+    reset_rcon_password(dst, &dst_path, src)?;
+    ports::allocate(dst, &dst_path)?;
 
     Ok(())
 }
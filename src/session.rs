@@ -1,6 +1,6 @@
 use crate::{
     error::{Error, Result},
-    server::{LastUsed, ServerObject, get_last_used, save_last_used_now},
+    server::{self, LastUsed, ServerObject, get_last_used, save_last_used_now},
     session,
 };
 use std::{
@@ -80,6 +80,14 @@ fn get_dead_server_sessions() -> Result<HashSet<String>> {
         .unwrap_or_default())
 }
 
+pub fn is_active(server: impl AsRef<str>) -> Result<bool> {
+    Ok(get_alive_server_sessions()?.contains(server.as_ref()))
+}
+
+pub fn is_dead(server: impl AsRef<str>) -> Result<bool> {
+    Ok(get_dead_server_sessions()?.contains(server.as_ref()))
+}
+
 fn get_server_sessions_to_living() -> Result<HashMap<String, bool>> {
     Ok(get_server_sessions_raw_string()?
         .map(|ss| {
@@ -163,6 +171,10 @@ pub fn tag_dead_servers(servers: &mut [ServerObject]) -> Result<()> {
 
 pub fn attach(server: impl AsRef<str>) -> Result<()> {
     let server = server.as_ref();
+    if server::is_template(server) {
+        return Err(Error::TemplateAttached);
+    }
+
     let mut child = Command::new(BASE_COMMAND)
         .arg("attach")
         .arg(get_name(server))
@@ -191,18 +203,25 @@ pub fn attach(server: impl AsRef<str>) -> Result<()> {
     }
 }
 
-pub fn new_session<S, I>(session: S, initial_command: Option<I>) -> Result<()>
-where
-    S: AsRef<OsStr>,
-    I: AsRef<OsStr>,
-{
+// a dead session with the same name would otherwise be resurrected
+fn clear_session(session: impl AsRef<OsStr>) -> Result<()> {
     Command::new(BASE_COMMAND)
         .arg("delete-session")
-        .arg(&session)
+        .arg(session)
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .status()?;
 
+    Ok(())
+}
+
+pub fn new_session<S, I>(session: S, initial_command: Option<I>) -> Result<()>
+where
+    S: AsRef<OsStr>,
+    I: AsRef<OsStr>,
+{
+    clear_session(&session)?;
+
     let mut command = Command::new(BASE_COMMAND);
     command.arg("--session").arg(&session);
     let mut child = command.spawn()?;
@@ -228,6 +247,46 @@ pub fn new_server(
     save_last_used_now(&server)
 }
 
+// starts a session without attaching to it, so that several servers can be started in a row
+pub fn new_detached_session<S, I>(session: S, initial_command: Option<I>) -> Result<()>
+where
+    S: AsRef<OsStr>,
+    I: AsRef<OsStr>,
+{
+    clear_session(&session)?;
+
+    let status = Command::new(BASE_COMMAND)
+        .arg("attach")
+        .arg("--create-background")
+        .arg(&session)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+
+    if !status.success() {
+        return Err(Error::CommandFailure {
+            code: status.code(),
+            stderr: None,
+        });
+    }
+
+    thread::sleep(Duration::from_millis(300));
+
+    if let Some(command) = initial_command {
+        write_line(&session, command)?;
+    }
+
+    Ok(())
+}
+
+pub fn new_detached_server(
+    server: impl Display + AsRef<Path>,
+    initial_command: Option<impl AsRef<OsStr>>,
+) -> Result<()> {
+    save_last_used_now(&server)?;
+    new_detached_session(get_name(&server), initial_command)
+}
+
 pub fn delete_server_session(server: impl Display, force: bool) -> Result<()> {
     let mut command = Command::new(BASE_COMMAND);
     command.arg("delete-session");
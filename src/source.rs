@@ -0,0 +1,370 @@
+use crate::{
+    archive, cache,
+    checksum::{self, Algorithm},
+    error::{Error, Result},
+    server::{self, METADATA_DIRECTORY},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    io, iter,
+    path::{self, Path, PathBuf},
+    process::{self, Command, Stdio},
+};
+use zip::ZipArchive;
+
+pub const ORIGIN_FILE: &str = "origin.toml";
+const TEMPLATES_DIRECTORY: &str = "templates";
+const ARCHIVES_DIRECTORY: &str = "archives";
+const GIT_DIRECTORY: &str = "git";
+const TREES_DIRECTORY: &str = "trees";
+
+const GIT_PREFIXES: [&str; 6] = ["http://", "https://", "ssh://", "git://", "file://", "git@"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Template,
+    Archive,
+    Git,
+}
+
+// where one layer of a server came from, recorded in `.mcserver/origin.toml`
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Origin {
+    pub kind: Kind,
+    pub source: String,
+    pub revision: Option<String>,
+    // the directory the layer was copied from, which may have changed or been removed since
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct OriginFile {
+    layers: Vec<Origin>,
+}
+
+pub struct Fetched {
+    pub name: String,
+    pub dir: PathBuf,
+    pub origin: Origin,
+}
+
+fn get_templates_cache_dir() -> Result<PathBuf> {
+    Ok(cache::get_cache_dir()?.join(TEMPLATES_DIRECTORY))
+}
+
+fn is_archive(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    [".tar.gz", ".tgz", ".zip"]
+        .iter()
+        .any(|extension| name.ends_with(extension))
+}
+
+fn get_archive_name(path: &Path) -> String {
+    let name = server::get_dir_name(path);
+    [".tar.gz", ".tgz", ".zip"]
+        .iter()
+        .find_map(|extension| name.strip_suffix(extension))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+fn is_git(location: &str) -> bool {
+    if GIT_PREFIXES
+        .iter()
+        .any(|prefix| location.starts_with(prefix))
+        || location.ends_with(".git")
+    {
+        return true;
+    }
+
+    // local repositories, bare or not, which need a separator to never be mistaken for a
+    // template name
+    let path = Path::new(location);
+    if !location.contains(path::MAIN_SEPARATOR) {
+        return false;
+    }
+
+    (path.join("HEAD").is_file() && path.join("objects").is_dir()) || path.join(".git").exists()
+}
+
+// git sources are written as `<url>[#[<ref>][:<subdirectory>]]`
+fn parse_git(spec: &str) -> (&str, Option<&str>, Option<&str>) {
+    let Some((url, fragment)) = spec.rsplit_once('#') else {
+        return (spec, None, None);
+    };
+
+    let (reference, subdir) = match fragment.split_once(':') {
+        Some((reference, subdir)) => (reference, Some(subdir.trim_matches('/'))),
+        None => (fragment, None),
+    };
+
+    (
+        url,
+        Some(reference).filter(|reference| !reference.is_empty()),
+        subdir.filter(|subdir| !subdir.is_empty()),
+    )
+}
+
+fn run_git<I, S>(args: I) -> Result<String>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let output = Command::new("git")
+        .args(args)
+        .stdin(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(Error::CommandFailure {
+            code: output.status.code(),
+            stderr: Some(output.stderr),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+// a unique sibling that is renamed into place, so that interrupted fetches never leave a
+// partial cache entry behind
+fn get_temporary_path(path: &Path) -> PathBuf {
+    path.with_file_name(format!(".{}-{}", server::get_dir_name(path), process::id()))
+}
+
+fn hash_str(value: &str) -> Result<String> {
+    Ok(checksum::copy(value.as_bytes(), io::sink(), Algorithm::Sha256)?.hash)
+}
+
+// archives that only contain a single directory use it as the template
+fn get_root(dir: &Path) -> Result<PathBuf> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+
+    match (entries.pop(), entries.is_empty()) {
+        (Some(entry), true) if entry.file_type()?.is_dir() => Ok(entry.path()),
+        _ => Ok(dir.to_path_buf()),
+    }
+}
+
+fn fetch_archive(path: &Path) -> Result<Fetched> {
+    let path = fs::canonicalize(path)?;
+    let sha256 = checksum::copy(File::open(&path)?, io::sink(), Algorithm::Sha256)?.hash;
+    let dir = get_templates_cache_dir()?
+        .join(ARCHIVES_DIRECTORY)
+        .join(&sha256);
+
+    if !dir.is_dir() {
+        let temporary = get_temporary_path(&dir);
+        fs::create_dir_all(&temporary)?;
+
+        let result = if path.to_string_lossy().ends_with(".zip") {
+            ZipArchive::new(File::open(&path)?)
+                .and_then(|mut archive| archive.extract(&temporary))
+                .map_err(Error::from)
+        } else {
+            archive::unpack(File::open(&path)?, &temporary)
+        }
+        .and_then(|()| Ok(fs::rename(&temporary, &dir)?));
+
+        if let Err(err) = result {
+            server::remove_dir_with_retries(&temporary)?;
+            return Err(err);
+        }
+    }
+
+    Ok(Fetched {
+        name: get_archive_name(&path),
+        dir: get_root(&dir)?,
+        origin: Origin {
+            kind: Kind::Archive,
+            source: path.to_string_lossy().to_string(),
+            revision: Some(format!("sha256:{sha256}")),
+            dir: None,
+        },
+    })
+}
+
+// keeps a mirror of each repository so that templates can still be used offline
+fn update_mirror(url: &str) -> Result<PathBuf> {
+    let mirror = get_templates_cache_dir()?
+        .join(GIT_DIRECTORY)
+        .join(&hash_str(url)?[..16]);
+
+    if mirror.is_dir() {
+        let fetch = run_git([
+            OsStr::new("--git-dir"),
+            mirror.as_os_str(),
+            OsStr::new("fetch"),
+            OsStr::new("--prune"),
+            OsStr::new("--quiet"),
+        ]);
+        if let Err(err) = fetch {
+            eprintln!("Warning: failed to fetch {url}, using the cached copy: {err}");
+        }
+        return Ok(mirror);
+    }
+
+    println!("Cloning {url}...");
+    fs::create_dir_all(mirror.parent().unwrap_or(&mirror))?;
+    let temporary = get_temporary_path(&mirror);
+
+    let result = run_git([
+        OsStr::new("clone"),
+        OsStr::new("--mirror"),
+        OsStr::new("--quiet"),
+        OsStr::new(url),
+        temporary.as_os_str(),
+    ])
+    .and_then(|_| Ok(fs::rename(&temporary, &mirror)?));
+
+    if let Err(err) = result {
+        if temporary.exists() {
+            server::remove_dir_with_retries(&temporary)?;
+        }
+        return Err(err);
+    }
+
+    Ok(mirror)
+}
+
+fn export_tree(mirror: &Path, tree: &str, dir: &Path) -> Result<()> {
+    let temporary = get_temporary_path(dir);
+    fs::create_dir_all(&temporary)?;
+
+    let result = (|| {
+        let mut child = Command::new("git")
+            .arg("--git-dir")
+            .arg(mirror)
+            .args(["archive", "--format=tar", tree])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        if let Some(stdout) = child.stdout.take() {
+            tar::Archive::new(stdout).unpack(&temporary)?;
+        }
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(Error::CommandFailure {
+                code: output.status.code(),
+                stderr: Some(output.stderr),
+            });
+        }
+
+        Ok(fs::rename(&temporary, dir)?)
+    })();
+
+    if let Err(err) = result {
+        server::remove_dir_with_retries(&temporary)?;
+        return Err(err);
+    }
+
+    Ok(())
+}
+
+fn fetch_git(spec: &str) -> Result<Fetched> {
+    let (location, reference, subdir) = parse_git(spec);
+
+    // local repositories are keyed by their absolute path
+    let url = match fs::canonicalize(location) {
+        Ok(path) => path.to_string_lossy().to_string(),
+        Err(_) => location.to_string(),
+    };
+
+    let mirror = update_mirror(&url)?;
+    let reference = reference.unwrap_or("HEAD");
+    let commit = run_git([
+        OsStr::new("--git-dir"),
+        mirror.as_os_str(),
+        OsStr::new("rev-parse"),
+        OsStr::new("--verify"),
+        OsStr::new(&format!("{reference}^{{commit}}")),
+    ])?;
+
+    // peeling doesn't apply to `<commit>:<path>`, which already names the tree
+    let tree_ish = match subdir {
+        Some(subdir) => format!("{commit}:{subdir}"),
+        None => format!("{commit}^{{tree}}"),
+    };
+    let tree = run_git([
+        OsStr::new("--git-dir"),
+        mirror.as_os_str(),
+        OsStr::new("rev-parse"),
+        OsStr::new("--verify"),
+        OsStr::new(&tree_ish),
+    ])?;
+
+    // trees are content addressed, so an export never has to be refreshed
+    let dir = get_templates_cache_dir()?.join(TREES_DIRECTORY).join(&tree);
+    if !dir.is_dir() {
+        export_tree(&mirror, &tree, &dir)?;
+    }
+
+    let name = subdir
+        .and_then(|subdir| subdir.rsplit('/').next())
+        .unwrap_or_else(|| {
+            let name = url.trim_end_matches('/');
+            let name = name.rsplit(['/', ':']).next().unwrap_or(name);
+            name.strip_suffix(".git").unwrap_or(name)
+        })
+        .to_string();
+
+    Ok(Fetched {
+        name,
+        dir,
+        origin: Origin {
+            kind: Kind::Git,
+            source: iter::once(url.as_str())
+                .chain(spec.rsplit_once('#').map(|(_, fragment)| fragment))
+                .collect::<Vec<_>>()
+                .join("#"),
+            revision: Some(commit),
+            dir: None,
+        },
+    })
+}
+
+// returns None for plain template names
+pub fn fetch(spec: &str) -> Result<Option<Fetched>> {
+    let path = Path::new(spec);
+
+    if is_archive(path) && path.is_file() {
+        return fetch_archive(path).map(Some);
+    }
+
+    if is_git(parse_git(spec).0) {
+        return fetch_git(spec).map(Some);
+    }
+
+    Ok(None)
+}
+
+pub fn record(server_dir: impl AsRef<Path>, layers: Vec<Origin>) -> Result<()> {
+    let metadata_dir = server_dir.as_ref().join(METADATA_DIRECTORY);
+    fs::create_dir_all(&metadata_dir)?;
+    fs::write(
+        metadata_dir.join(ORIGIN_FILE),
+        toml::to_string(&OriginFile { layers })?,
+    )?;
+    Ok(())
+}
+
+pub fn read(server_dir: impl AsRef<Path>) -> Result<Option<Vec<Origin>>> {
+    let path = server_dir
+        .as_ref()
+        .join(METADATA_DIRECTORY)
+        .join(ORIGIN_FILE);
+
+    if !path.is_file() {
+        return Ok(None);
+    }
+
+    Ok(Some(
+        toml::from_str::<OriginFile>(&fs::read_to_string(path)?)?.layers,
+    ))
+}
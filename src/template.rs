@@ -0,0 +1,579 @@
+use crate::{
+    config::{get_expanded_servers_dir, get_expanded_templates_dir},
+    drift,
+    error::{Error, Result},
+    exclude::{self, Excludes},
+    overlay, ports,
+    server::{self, METADATA_DIRECTORY, TEMPLATE_SUFFIX},
+    session,
+    source::{self, Fetched, Kind, Origin},
+};
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, IsTerminal, Write},
+    iter,
+    path::{Path, PathBuf},
+};
+
+pub const MANIFEST_FILE: &str = "template.toml";
+
+const TEXT_EXTENSIONS: [&str; 10] = [
+    "properties",
+    "yml",
+    "yaml",
+    "toml",
+    "json",
+    "json5",
+    "conf",
+    "cfg",
+    "ini",
+    "txt",
+];
+
+#[derive(Debug, Deserialize)]
+struct Variable {
+    name: String,
+    prompt: Option<String>,
+    default: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Manifest {
+    #[serde(default)]
+    variables: Vec<Variable>,
+}
+
+impl Manifest {
+    // variables declared by later layers replace earlier declarations with the same name
+    fn extend(&mut self, other: Manifest) {
+        for variable in other.variables {
+            match self.variables.iter_mut().find(|v| v.name == variable.name) {
+                Some(existing) => *existing = variable,
+                None => self.variables.push(variable),
+            }
+        }
+    }
+}
+
+fn read_manifest(server_dir: &Path) -> Result<Manifest> {
+    let path = server_dir.join(METADATA_DIRECTORY).join(MANIFEST_FILE);
+
+    if !path.is_file() {
+        return Ok(Manifest::default());
+    }
+
+    Ok(toml::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn prompt(variable: &Variable) -> Result<String> {
+    let label = variable.prompt.as_deref().unwrap_or(&variable.name);
+
+    loop {
+        match &variable.default {
+            Some(default) => print!("{label} [{default}]: "),
+            None => print!("{label}: "),
+        }
+        io::stdout().flush()?;
+
+        let mut value = String::new();
+        if io::stdin().read_line(&mut value)? == 0 {
+            println!();
+            break Err(Error::MissingTemplateVariable(variable.name.clone()));
+        }
+        let value = value.trim_end_matches(['\n', '\r']);
+
+        match (value.is_empty(), &variable.default) {
+            (true, Some(default)) => break Ok(default.clone()),
+            (true, None) => continue,
+            (false, _) => break Ok(value.to_string()),
+        }
+    }
+}
+
+// values come from `--set`, then an interactive prompt, then the declared default
+fn resolve_values(
+    manifest: Manifest,
+    mut assignments: HashMap<String, String>,
+) -> Result<HashMap<String, String>> {
+    let interactive = io::stdin().is_terminal();
+    let mut values = HashMap::new();
+
+    for variable in manifest.variables {
+        let value = match assignments.remove(&variable.name) {
+            Some(value) => value,
+            None if interactive => prompt(&variable)?,
+            None => variable
+                .default
+                .clone()
+                .ok_or_else(|| Error::MissingTemplateVariable(variable.name.clone()))?,
+        };
+
+        values.insert(variable.name, value);
+    }
+
+    for (name, value) in assignments {
+        eprintln!("Warning: {name} is not declared by the template");
+        values.insert(name, value);
+    }
+
+    Ok(values)
+}
+
+// replaces `{{name}}` placeholders, leaving unknown ones untouched
+fn substitute(
+    contents: &str,
+    values: &HashMap<String, String>,
+    unknown: &mut Vec<String>,
+) -> String {
+    let mut output = String::with_capacity(contents.len());
+    let mut rest = contents;
+
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+
+        let name = after[..end].trim();
+        match values.get(name) {
+            Some(value) => output.push_str(value),
+            None => {
+                if !name.is_empty()
+                    && name
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+                {
+                    unknown.push(name.to_string());
+                }
+                output.push_str(&rest[start..start + 2 + end + 2]);
+            }
+        }
+
+        rest = &after[end + 2..];
+    }
+
+    output.push_str(rest);
+    output
+}
+
+pub fn is_text_config(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| TEXT_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+fn substitute_dir(
+    dir: &Path,
+    values: &HashMap<String, String>,
+    unknown: &mut Vec<String>,
+) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            if entry.file_name() != METADATA_DIRECTORY {
+                substitute_dir(&path, values, unknown)?;
+            }
+            continue;
+        }
+
+        if !file_type.is_file() || !is_text_config(&path) {
+            continue;
+        }
+
+        // binary files that happen to use a config extension are skipped
+        let Ok(contents) = fs::read_to_string(&path) else {
+            continue;
+        };
+
+        if contents.contains("{{") {
+            let substituted = substitute(&contents, values, unknown);
+            if substituted != contents {
+                fs::write(&path, substituted)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// fills in the variables of a server that was just copied from its templates
+fn instantiate(
+    server_dir: &Path,
+    manifest: Manifest,
+    builtins: HashMap<String, String>,
+    assignments: HashMap<String, String>,
+) -> Result<()> {
+    let mut values = resolve_values(manifest, assignments)?;
+
+    for (name, value) in builtins {
+        values.entry(name).or_insert(value);
+    }
+
+    let mut unknown = vec![];
+    substitute_dir(server_dir, &values, &mut unknown)?;
+
+    unknown.sort();
+    unknown.dedup();
+    if !unknown.is_empty() {
+        eprintln!(
+            "Warning: no values were given for {}",
+            unknown
+                .iter()
+                .map(|name| format!("{{{{{name}}}}}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    let manifest_path = server_dir.join(METADATA_DIRECTORY).join(MANIFEST_FILE);
+    if manifest_path.is_file() {
+        fs::remove_file(manifest_path)?;
+    }
+
+    Ok(())
+}
+
+fn get_name(template: &str) -> &str {
+    template.strip_suffix(TEMPLATE_SUFFIX).unwrap_or(template)
+}
+
+// templates created before the templates directory existed live next to servers as `<name>.template`
+fn get_legacy_dir(name: &str) -> Result<PathBuf> {
+    Ok(get_expanded_servers_dir()?.join(format!("{name}{TEMPLATE_SUFFIX}")))
+}
+
+fn find(template: &str) -> Result<Option<PathBuf>> {
+    let name = get_name(template);
+
+    let path = get_expanded_templates_dir()?.join(name);
+    if path.is_dir() {
+        return Ok(Some(path));
+    }
+
+    let legacy_path = get_legacy_dir(name)?;
+    Ok(legacy_path.is_dir().then_some(legacy_path))
+}
+
+pub fn get_template_dir(template: impl AsRef<str>) -> Result<PathBuf> {
+    let template = template.as_ref();
+    find(template)?.ok_or_else(|| Error::TemplateNotFound(get_name(template).to_string()))
+}
+
+fn is_legacy(template_dir: &Path) -> bool {
+    template_dir
+        .file_name()
+        .is_some_and(|name| server::is_template(name.to_string_lossy()))
+}
+
+// returns the name and directory of every template, sorted by name
+pub fn get_all() -> Result<Vec<(String, PathBuf)>> {
+    let mut templates = HashMap::new();
+
+    let servers_dir = get_expanded_servers_dir()?;
+    if servers_dir.is_dir() {
+        for entry in fs::read_dir(servers_dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if let Some(name) = file_name.strip_suffix(TEMPLATE_SUFFIX)
+                && entry.file_type()?.is_dir()
+            {
+                templates.insert(name.to_string(), entry.path());
+            }
+        }
+    }
+
+    // templates in the templates directory take precedence over legacy ones with the same name
+    let templates_dir = get_expanded_templates_dir()?;
+    if templates_dir.is_dir() {
+        for entry in fs::read_dir(templates_dir)? {
+            let entry = entry?;
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if !file_name.starts_with('.') && entry.file_type()?.is_dir() {
+                templates.insert(file_name, entry.path());
+            }
+        }
+    }
+
+    let mut templates: Vec<_> = templates.into_iter().collect();
+    templates.sort();
+    Ok(templates)
+}
+
+pub fn new(server: impl AsRef<str>, excludes: &[String]) -> Result<()> {
+    let server = server.as_ref();
+    if server::is_template(server) {
+        return Err(Error::TemplateUsedForTemplate);
+    }
+    println!("Creating template using server {server}...");
+
+    let server_path = get_expanded_servers_dir()?.join(server);
+    if !server_path.is_dir() {
+        return Err(Error::ServerNotFound(server.to_string()));
+    }
+
+    if find(server)?.is_some() {
+        return Err(Error::TemplateAlreadyExists(server.to_string()));
+    }
+
+    let excludes = Excludes::new(&server_path, excludes)?;
+    let templates_dir = get_expanded_templates_dir()?;
+    fs::create_dir_all(templates_dir)?;
+    exclude::copy_directory(&server_path, templates_dir.join(server), &excludes)?;
+
+    Ok(())
+}
+
+// overlays are layered over the template in order, see `overlay::apply`
+pub fn create_server(
+    template: impl AsRef<str>,
+    overlays: &[String],
+    server: Option<impl AsRef<str>>,
+    assignments: HashMap<String, String>,
+) -> Result<()> {
+    let mut names = vec![];
+    let mut template_paths = vec![];
+    let mut origins = vec![];
+
+    for spec in iter::once(template.as_ref()).chain(overlays.iter().map(String::as_str)) {
+        let fetched = match source::fetch(spec)? {
+            Some(fetched) => fetched,
+            None => {
+                let name = get_name(spec);
+                Fetched {
+                    name: name.to_string(),
+                    dir: get_template_dir(name)?,
+                    origin: Origin {
+                        kind: Kind::Template,
+                        source: name.to_string(),
+                        revision: None,
+                        dir: None,
+                    },
+                }
+            }
+        };
+
+        names.push(fetched.name);
+        origins.push(Origin {
+            dir: Some(fetched.dir.clone()),
+            ..fetched.origin
+        });
+        template_paths.push(fetched.dir);
+    }
+
+    // the most specific layer names the server
+    let template = &names[names.len() - 1];
+
+    if names.len() == 1 {
+        println!("Creating server from template {template}");
+    } else {
+        println!("Creating server from templates {}", names.join(", "));
+    }
+
+    let server_path = match server {
+        Some(server) => {
+            let server = server.as_ref();
+            let path = get_expanded_servers_dir()?.join(server);
+            if path.exists() {
+                return Err(Error::ServerAlreadyExists(server.to_string()));
+            }
+            path
+        }
+        None => server::get_first_server_path(template)?,
+    };
+
+    let server = server::get_dir_name(&server_path);
+    let result = (|| {
+        let mut manifest = Manifest::default();
+        server::copy_directory(&template_paths[0], &server_path)?;
+        manifest.extend(read_manifest(&template_paths[0])?);
+
+        for template_path in &template_paths[1..] {
+            overlay::apply(&server_path, template_path)?;
+            manifest.extend(read_manifest(template_path)?);
+        }
+
+        let builtins = HashMap::from([("server".to_string(), server.clone())]);
+        instantiate(&server_path, manifest, builtins, assignments)?;
+        source::record(&server_path, origins)
+    })();
+
+    if let Err(err) = result {
+        if server_path.exists() {
+            server::remove_dir_with_retries(&server_path)?;
+        }
+        return Err(err);
+    }
+
+    server::reset_rcon_password(&server, &server_path, &names[0])?;
+    ports::allocate(&server, &server_path)?;
+
+    // recorded last so that a new server starts without any drift
+    drift::record(&server_path)
+}
+
+pub fn list() -> Result<()> {
+    for (name, path) in get_all()? {
+        if is_legacy(&path) {
+            println!("{name} (legacy)");
+        } else {
+            println!("{name}");
+        }
+    }
+
+    Ok(())
+}
+
+pub fn show(template: impl AsRef<str>) -> Result<()> {
+    let template = get_name(template.as_ref());
+    let template_dir = get_template_dir(template)?;
+
+    println!("Template: {template}");
+    println!("Path: {}", template_dir.display());
+    if is_legacy(&template_dir) {
+        println!("Location: servers directory (legacy, move it with `template rename`)");
+    }
+
+    let jar_info = server::get_jar_info(&template_dir)?.unwrap_or_default();
+    if let Some(platform) = jar_info.platform {
+        println!("Platform: {platform}");
+    }
+    if let Some(version) = jar_info.version {
+        println!("Version: {version}");
+    }
+    if let Some(build) = jar_info.build {
+        println!("Build: {build}");
+    }
+
+    let manifest = read_manifest(&template_dir)?;
+    if !manifest.variables.is_empty() {
+        println!("Variables:");
+        for variable in manifest.variables {
+            let mut line = format!("  {}", variable.name);
+            if let Some(default) = variable.default {
+                line.push_str(&format!(" (default: {default})"));
+            }
+            if let Some(prompt) = variable.prompt {
+                line.push_str(&format!(" - {prompt}"));
+            }
+            println!("{line}");
+        }
+    }
+
+    Ok(())
+}
+
+pub fn delete(templates: Vec<String>, force: bool) -> Result<()> {
+    for template in templates {
+        let name = get_name(&template);
+        let template_dir = get_template_dir(name)?;
+
+        if !force
+            && !loop {
+                print!("Enter `{name}` to delete the template or nothing to cancel operation: ");
+                io::stdout().flush()?;
+
+                let mut response = String::new();
+                io::stdin().read_line(&mut response)?;
+
+                if name == response.trim_end() {
+                    break true;
+                } else if response.is_empty() {
+                    break false;
+                }
+            }
+        {
+            println!("Operation canceled");
+            continue;
+        }
+
+        server::remove_dir_with_retries(template_dir)?;
+        println!("Template {name} successfully removed");
+    }
+
+    Ok(())
+}
+
+// renaming a legacy template also moves it into the templates directory
+pub fn rename(old: impl AsRef<str>, new: impl AsRef<str>) -> Result<()> {
+    let (old, new) = (get_name(old.as_ref()), get_name(new.as_ref()));
+    let old_path = get_template_dir(old)?;
+
+    let templates_dir = get_expanded_templates_dir()?;
+    let new_path = templates_dir.join(new);
+    if find(new)?.is_some_and(|path| path != old_path) {
+        return Err(Error::TemplateAlreadyExists(new.to_string()));
+    }
+
+    fs::create_dir_all(templates_dir)?;
+    server::move_directory(&old_path, &new_path)?;
+
+    if is_legacy(&old_path) {
+        println!(
+            "Moved {old} from the servers directory to {}",
+            new_path.display()
+        );
+    }
+    if old != new {
+        println!("Renamed template {old} to {new}");
+    }
+
+    Ok(())
+}
+
+// replaces the contents of a template with a server, keeping the template's manifest
+pub fn update_from(
+    template: impl AsRef<str>,
+    server: impl AsRef<str>,
+    excludes: &[String],
+) -> Result<()> {
+    let (template, server) = (get_name(template.as_ref()), server.as_ref());
+    let template_dir = get_template_dir(template)?;
+
+    let server_dir = get_expanded_servers_dir()?.join(server);
+    if server::is_template(server) || !server_dir.is_dir() {
+        return Err(Error::ServerNotFound(server.to_string()));
+    }
+
+    if session::is_active(server)? {
+        eprintln!("Warning: {server} is running, so its files may change while being copied");
+    }
+
+    let excludes = Excludes::new(&server_dir, excludes)?;
+    let parent = template_dir.parent().unwrap_or(&template_dir);
+    let staging_dir = parent.join(format!(".update-{template}"));
+    if staging_dir.exists() {
+        server::remove_dir_with_retries(&staging_dir)?;
+    }
+
+    let result = (|| {
+        exclude::copy_directory(&server_dir, &staging_dir, &excludes)?;
+
+        let manifest_path = template_dir.join(METADATA_DIRECTORY).join(MANIFEST_FILE);
+        if manifest_path.is_file() {
+            let metadata_dir = staging_dir.join(METADATA_DIRECTORY);
+            fs::create_dir_all(&metadata_dir)?;
+            fs::copy(&manifest_path, metadata_dir.join(MANIFEST_FILE))?;
+        }
+
+        Ok(())
+    })();
+
+    if let Err(err) = result {
+        server::remove_dir_with_retries(&staging_dir)?;
+        return Err(err);
+    }
+
+    server::remove_dir_with_retries(&template_dir)?;
+    fs::rename(&staging_dir, &template_dir)?;
+
+    println!("Updated template {template} from {server}");
+    Ok(())
+}
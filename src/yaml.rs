@@ -0,0 +1,201 @@
+use serde_norway::Value;
+
+// edits block style yaml line by line instead of re-serializing it, so that comments, key order and
+// formatting survive. set and merge return None when the document is laid out in a way they can't
+// edit safely, e.g. flow style mappings or multi-line values, leaving the caller to fall back
+
+fn get_indent(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+fn is_content(line: &str) -> bool {
+    let trimmed = line.trim();
+    !trimmed.is_empty() && !trimmed.starts_with('#')
+}
+
+fn get_first_indent(lines: &[String]) -> Option<usize> {
+    lines
+        .iter()
+        .find(|line| is_content(line))
+        .map(|line| get_indent(line))
+}
+
+// splits `key: value` into the key, unquoted, and the byte offset of the colon
+fn parse_key(line: &str) -> Option<(String, usize)> {
+    let indent = get_indent(line);
+    let trimmed = &line[indent..];
+
+    let (key, colon) = match trimmed.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = trimmed[1..].find(quote)? + 1;
+            (trimmed[1..end].to_string(), end + 1)
+        }
+        '-' | '#' | '{' | '[' => return None,
+        _ => {
+            let colon = trimmed
+                .match_indices(':')
+                .map(|(index, _)| index)
+                .find(|&index| {
+                    trimmed[index + 1..].is_empty() || trimmed[index + 1..].starts_with(' ')
+                })?;
+            (trimmed[..colon].trim_end().to_string(), colon)
+        }
+    };
+
+    trimmed[colon..]
+        .starts_with(':')
+        .then_some((key, indent + colon))
+}
+
+// a mapping entry ends at the next line that isn't indented further, although a sequence may be
+// written at the same indentation as its key
+fn get_block_end(lines: &[String], index: usize, indent: usize) -> usize {
+    lines[index + 1..]
+        .iter()
+        .position(|line| {
+            is_content(line)
+                && (get_indent(line) < indent
+                    || get_indent(line) == indent && !line.trim_start().starts_with('-'))
+        })
+        .map_or(lines.len(), |position| index + 1 + position)
+}
+
+fn render_scalar(value: &Value) -> Option<String> {
+    let rendered = serde_norway::to_string(value).ok()?;
+    let rendered = rendered.trim_end();
+    (!rendered.contains('\n')).then(|| rendered.to_string())
+}
+
+// scalars are written after their key, while mappings and sequences go on the lines below it
+fn render(value: &Value, indent: usize) -> Option<(String, Vec<String>)> {
+    match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => {}
+        Value::Sequence(sequence) if !sequence.is_empty() => {}
+        Value::Tagged(_) => return None,
+        value => return Some((format!(" {}", render_scalar(value)?), vec![])),
+    }
+
+    let indent = " ".repeat(indent);
+    let block = serde_norway::to_string(value)
+        .ok()?
+        .lines()
+        .map(|line| format!("{indent}{line}"))
+        .collect();
+    Some((String::new(), block))
+}
+
+fn get<'a>(document: &'a Value, keys: &[&str]) -> Option<&'a Value> {
+    keys.iter().try_fold(document, |value, key| value.get(*key))
+}
+
+// sets the value at a nested key, adding whichever mappings are missing at the end of their parent
+pub fn set(contents: &str, keys: &[&str], value: &Value) -> Option<String> {
+    let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+    let (mut start, mut end, mut parent_indent) = (0, lines.len(), None);
+
+    for (depth, key) in keys.iter().enumerate() {
+        let default_indent = parent_indent.map_or(0, |indent| indent + 2);
+        let indent = get_first_indent(&lines[start..end]).unwrap_or(default_indent);
+        if parent_indent.is_some_and(|parent_indent| indent <= parent_indent) {
+            return None;
+        }
+
+        let index = (start..end).find(|&index| {
+            get_indent(&lines[index]) == indent
+                && parse_key(&lines[index]).is_some_and(|(found, _)| found == *key)
+        });
+        let is_last = depth == keys.len() - 1;
+
+        let Some(index) = index else {
+            let mut at = end;
+            while at > start && !is_content(&lines[at - 1]) {
+                at -= 1;
+            }
+
+            let mut added = vec![];
+            for (offset, key) in keys[depth..].iter().enumerate() {
+                let key = render_scalar(&Value::String(key.to_string()))?;
+                let indent = " ".repeat(indent + offset * 2);
+                added.push(format!("{indent}{key}:"));
+            }
+
+            let (inline, block) = render(value, indent + (keys.len() - depth) * 2)?;
+            if let Some(last) = added.last_mut() {
+                last.push_str(&inline);
+            }
+            added.extend(block);
+            lines.splice(at..at, added);
+            break;
+        };
+
+        let (_, colon) = parse_key(&lines[index])?;
+        let block_end = get_block_end(&lines, index, indent);
+        let inline = lines[index][colon + 1..].trim();
+        let inline = inline.split(" #").next().unwrap_or_default().trim();
+
+        if is_last {
+            let (inline, block) = render(value, indent + 2)?;
+
+            // comments can only be told apart from the value when it isn't quoted
+            let rest = &lines[index][colon + 1..];
+            let comment = if rest.trim_start().starts_with(['"', '\'']) {
+                String::new()
+            } else {
+                rest.find(" #")
+                    .map_or(String::new(), |start| rest[start..].to_string())
+            };
+            lines[index] = format!("{}:{inline}{comment}", &lines[index][..colon]);
+
+            // the old value's lines are replaced, but the comments after them belong to the next key
+            let mut children_end = block_end;
+            while children_end > index + 1 && !is_content(&lines[children_end - 1]) {
+                children_end -= 1;
+            }
+            lines.splice(index + 1..children_end, block);
+        } else {
+            if !inline.is_empty() && !inline.starts_with('#') {
+                return None;
+            }
+            (start, end, parent_indent) = (index + 1, block_end, Some(indent));
+        }
+    }
+
+    let mut edited = lines.join("\n");
+    edited.push('\n');
+
+    // the edit is only trusted if the document still parses to what was asked for
+    let document: Value = serde_norway::from_str(&edited).ok()?;
+    (get(&document, keys) == Some(value)).then_some(edited)
+}
+
+fn collect_leaves<'a>(
+    value: &'a Value,
+    keys: &mut Vec<&'a str>,
+    leaves: &mut Vec<(Vec<&'a str>, &'a Value)>,
+) -> Option<()> {
+    match value {
+        Value::Mapping(mapping) if !mapping.is_empty() => {
+            for (key, value) in mapping {
+                keys.push(key.as_str()?);
+                collect_leaves(value, keys, leaves)?;
+                keys.pop();
+            }
+        }
+        value if !keys.is_empty() => leaves.push((keys.clone(), value)),
+        _ => return None,
+    }
+
+    Some(())
+}
+
+// sets every value of an overlay mapping in the document, replacing sequences as a whole
+pub fn merge(contents: &str, overlay: &Value) -> Option<String> {
+    let mut leaves = vec![];
+    collect_leaves(overlay, &mut vec![], &mut leaves)?;
+
+    leaves
+        .into_iter()
+        .try_fold(contents.to_string(), |contents, (keys, value)| {
+            set(&contents, &keys, value)
+        })
+}